@@ -0,0 +1,482 @@
+use std::{cmp::Ordering, io::Write};
+use anyhow::{Result, anyhow};
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive};
+
+use crate::interpreter::{Environment, Object};
+use crate::lexer::VarType;
+use crate::ops::{self, Format, Integers};
+use crate::types::Type;
+
+/// Cum primește o funcție predefinită un argument.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArgKind {
+    /// Valoarea expresiei, calculată înaintea apelului.
+    Value,
+    /// O variabilă, eventual cu adnotare de tip, care primește o valoare.
+    Reference,
+}
+
+/// Un argument gata de folosit de funcție.
+pub enum Arg {
+    Value(Object),
+    Reference(String, Option<VarType>),
+}
+
+/// Fluxul înlocuit de un fișier în `citește din ...` / `scrie în ...`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stream {
+    Input,
+    Output,
+}
+
+pub struct Builtin {
+    pub name: &'static str,
+    /// Numărul minim de argumente și, dacă există, cel maxim.
+    pub arity: (usize, Option<usize>),
+    /// Felul fiecărui argument; ultimul se repetă pentru cele în plus.
+    pub args: &'static [ArgKind],
+    /// Cuvântul care introduce un fișier înaintea argumentelor și fluxul pe
+    /// care îl înlocuiește.
+    pub file: Option<(&'static str, Stream)>,
+    pub call: fn(&mut Environment, Vec<Arg>) -> Result<Object>,
+}
+
+impl Builtin {
+    pub fn kind(&self, i: usize) -> ArgKind {
+        self.args[i.min(self.args.len() - 1)]
+    }
+
+    /// Funcția atribuie variabile, ca `citește`.
+    pub fn assigns(&self) -> bool {
+        self.args.contains(&ArgKind::Reference)
+    }
+
+    pub fn check_arity(&self, count: usize) -> Result<()> {
+        let (min, max) = self.arity;
+        if count >= min && max.is_none_or(|max| count <= max) {
+            return Ok(());
+        }
+        let (expected, n) = match max {
+            Some(max) if min == max => (min.to_string(), min),
+            Some(max) if count > max => (format!("cel mult {}", max), max),
+            _ => (format!("cel puțin {}", min), min),
+        };
+        let noun = if n == 1 { "argument" } else { "argumente" };
+        Err(anyhow!("Funcția `{}` primește {} {}, nu {}.", self.name, expected, noun, count))
+    }
+}
+
+fn scrie(env: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    let mut written = 0;
+    for arg in args {
+        if let Arg::Value(value) = arg {
+            let shown = env.show(&value, None);
+            written += shown.chars().count();
+            write!(env.output, "{}", shown)?;
+            env.output.flush()?;
+        }
+    }
+    Ok(Object::Int(written as i64))
+}
+
+fn citeste(env: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    for arg in args {
+        if let Arg::Reference(name, t) = arg {
+            let value = env.read_value(&name, t)?;
+            match t {
+                Some(t) => env.declared.insert(name.clone(), Type::from(t)),
+                None => env.declared.remove(&name),
+            };
+            env.find_variable(&name, Some(Box::new(value)));
+        }
+    }
+    Ok(Object::Null)
+}
+
+fn values(args: Vec<Arg>) -> Vec<Object> {
+    args.into_iter().filter_map(|arg| match arg {
+        Arg::Value(value) => Some(value),
+        Arg::Reference(..) => None,
+    }).collect()
+}
+
+fn number(function: &str, value: &Object) -> Result<f64> {
+    match value {
+        Object::Int(x) => Ok(*x as f64),
+        Object::Float(x) => Ok(*x),
+        Object::BigInt(x) => x.to_f64().filter(|x| x.is_finite())
+            .ok_or_else(|| anyhow!("Numărul {} este prea mare pentru funcția `{}`.", x, function)),
+        Object::Rational(x) => Ok(x.to_f64().unwrap_or(f64::INFINITY)),
+        _ => Err(anyhow!("Funcția `{}` lucrează doar cu numere, nu cu `{}`.", function, value)),
+    }
+}
+
+fn integer(function: &str, value: &Object) -> Result<i64> {
+    match value {
+        Object::Int(x) => Ok(*x),
+        Object::BigInt(x) => Err(anyhow!("Numărul {} este prea mare pentru funcția `{}`.", x, function)),
+        _ => Err(anyhow!("Funcția `{}` lucrează doar cu numere întregi, nu cu `{}`.", function, value)),
+    }
+}
+
+fn extreme(name: &str, args: Vec<Arg>, pick: Ordering) -> Result<Object> {
+    let mut best: Option<Object> = None;
+    for value in values(args) {
+        if !matches!(value, Object::Int(_) | Object::BigInt(_) | Object::Rational(_) | Object::Float(_)) {
+            return Err(anyhow!("Funcția `{}` lucrează doar cu numere, nu cu `{}`.", name, value));
+        }
+        if best.as_ref().is_none_or(|b| ops::compare(&value, b) == Some(pick)) {
+            best = Some(value);
+        }
+    }
+    Ok(best.unwrap_or(Object::Null))
+}
+
+fn max(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    extreme("max", args, Ordering::Greater)
+}
+
+fn min(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    extreme("min", args, Ordering::Less)
+}
+
+fn abs(env: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    match &values(args)[0] {
+        Object::Int(x) => match x.checked_abs() {
+            Some(x) => Ok(Object::Int(x)),
+            None if env.arithmetic.integers == Integers::Big => Ok(ops::int(BigInt::from(*x).abs())),
+            None => Err(anyhow!("|{}| este prea mare pentru un număr întreg.", x)),
+        },
+        Object::BigInt(x) => Ok(Object::BigInt(x.abs())),
+        Object::Rational(x) => Ok(Object::Rational(x.abs())),
+        value => Ok(Object::Float(number("abs", value)?.abs())),
+    }
+}
+
+fn sqrt(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    let x = number("sqrt", &values(args)[0])?;
+    if x < 0.0 {
+        return Err(anyhow!("Nu există radical dintr-un număr negativ ({}).", x));
+    }
+    Ok(Object::Float(x.sqrt()))
+}
+
+fn pow(env: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    let mut args = values(args);
+    let exponent = args.pop().unwrap();
+    ops::power(env.arithmetic, &args.pop().unwrap(), &exponent)
+}
+
+fn cmmdc(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    let mut result: u64 = 0;
+    for value in values(args) {
+        let mut b = integer("cmmdc", &value)?.unsigned_abs();
+        while b != 0 {
+            (result, b) = (b, result % b);
+        }
+    }
+    i64::try_from(result).map(Object::Int).map_err(|_| anyhow!("Cel mai mare divizor comun este prea mare pentru un număr întreg."))
+}
+
+/// `a * b mod m`, fără depășire.
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+fn pow_mod(mut a: u64, mut e: u64, m: u64) -> u64 {
+    let mut result = 1;
+    while e > 0 {
+        if e & 1 == 1 {
+            result = mul_mod(result, a, m);
+        }
+        a = mul_mod(a, a, m);
+        e >>= 1;
+    }
+    result
+}
+
+/// Testul Miller-Rabin: cu primele 12 numere prime ca baze este exact pentru
+/// orice număr pe 64 de biți și face cel mult câteva mii de înmulțiri, deci
+/// nu are nevoie de limitele de pași.
+fn prim(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    let n = integer("prim", &values(args)[0])?;
+    if n < 2 {
+        return Ok(Object::Bool(false));
+    }
+    let n = n as u64;
+    if let Some(p) = BASES.iter().find(|p| n.is_multiple_of(**p)) {
+        return Ok(Object::Bool(n == *p));
+    }
+    let (d, s) = ((n - 1) >> (n - 1).trailing_zeros(), (n - 1).trailing_zeros());
+    'bases: for a in BASES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'bases;
+            }
+        }
+        return Ok(Object::Bool(false));
+    }
+    Ok(Object::Bool(true))
+}
+
+fn string(function: &str, value: &Object) -> Result<String> {
+    match value {
+        Object::String(x) => Ok(x.clone()),
+        _ => Err(anyhow!("Funcția `{}` lucrează doar cu șiruri de caractere, nu cu `{}`.", function, value)),
+    }
+}
+
+/// O poziție dintr-un șir de `len` caractere; `end` acceptă și poziția de
+/// după ultimul caracter.
+fn position(function: &str, value: &Object, s: &str, len: usize, end: bool) -> Result<usize> {
+    let i = integer(function, value)?;
+    if i < 0 || i as usize > len || (i as usize == len && !end) {
+        return Err(anyhow!("Poziția {} este în afara șirului \"{}\", care are {} caractere.", i, s, len));
+    }
+    Ok(i as usize)
+}
+
+fn lungime(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    Ok(Object::Int(string("lungime", &values(args)[0])?.chars().count() as i64))
+}
+
+fn subsir(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    let args = values(args);
+    let s = string("subșir", &args[0])?;
+    let len = s.chars().count();
+    let start = position("subșir", &args[1], &s, len, true)?;
+    let count = match args.get(2) {
+        Some(value) => {
+            let count = integer("subșir", value)?;
+            if count < 0 {
+                return Err(anyhow!("Lungimea unui subșir nu poate fi negativă ({}).", count));
+            }
+            count as usize
+        }
+        None => len,
+    };
+    Ok(Object::String(s.chars().skip(start).take(count).collect()))
+}
+
+fn litera(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    let args = values(args);
+    let s = string("litera", &args[0])?;
+    let i = position("litera", &args[1], &s, s.chars().count(), false)?;
+    Ok(Object::String(s.chars().nth(i).unwrap().to_string()))
+}
+
+fn cauta(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    let args = values(args);
+    let (s, needle) = (string("caută", &args[0])?, string("caută", &args[1])?);
+    Ok(Object::Int(match s.find(&needle) {
+        Some(byte) => s[..byte].chars().count() as i64,
+        None => -1,
+    }))
+}
+
+fn majuscule(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    Ok(Object::String(string("majuscule", &values(args)[0])?.to_uppercase()))
+}
+
+fn minuscule(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    Ok(Object::String(string("minuscule", &values(args)[0])?.to_lowercase()))
+}
+
+fn numar(env: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    let value = values(args).remove(0);
+    let s = match &value {
+        Object::Int(_) | Object::BigInt(_) | Object::Rational(_) | Object::Float(_) => return Ok(value),
+        Object::String(s) => s.trim(),
+        _ => return Err(anyhow!("Funcția `număr` lucrează doar cu șiruri de caractere și numere, nu cu `{}`.", value)),
+    };
+    if let Some(x) = env.parse_integer(s) {
+        return Ok(x);
+    }
+    match s.parse::<f64>() {
+        Ok(x) if x.is_finite() => Ok(Object::Float(x)),
+        _ => Err(anyhow!("\"{}\" nu este un număr.", s)),
+    }
+}
+
+/// `text(x)` nu depinde de opțiunile de afișare, ca `număr(text(x))` să dea
+/// înapoi `x`.
+fn text(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    Ok(Object::String(Format::default().show(&values(args)[0], false)))
+}
+
+fn cod(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    let s = string("cod", &values(args)[0])?;
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(Object::Int(c as i64)),
+        _ => Err(anyhow!("Funcția `cod` primește un singur caracter, nu \"{}\".", s)),
+    }
+}
+
+fn caracter(_: &mut Environment, args: Vec<Arg>) -> Result<Object> {
+    let code = integer("caracter", &values(args)[0])?;
+    u32::try_from(code).ok().and_then(char::from_u32)
+        .map(|c| Object::String(c.to_string()))
+        .ok_or_else(|| anyhow!("{} nu este codul unui caracter.", code))
+}
+
+pub static BUILTINS: &[Builtin] = &[
+    Builtin { name: "scrie", arity: (0, None), args: &[ArgKind::Value], file: Some(("în", Stream::Output)), call: scrie },
+    Builtin { name: "citește", arity: (1, None), args: &[ArgKind::Reference], file: Some(("din", Stream::Input)), call: citeste },
+    Builtin { name: "max", arity: (2, None), args: &[ArgKind::Value], file: None, call: max },
+    Builtin { name: "min", arity: (2, None), args: &[ArgKind::Value], file: None, call: min },
+    Builtin { name: "abs", arity: (1, Some(1)), args: &[ArgKind::Value], file: None, call: abs },
+    Builtin { name: "sqrt", arity: (1, Some(1)), args: &[ArgKind::Value], file: None, call: sqrt },
+    Builtin { name: "pow", arity: (2, Some(2)), args: &[ArgKind::Value], file: None, call: pow },
+    Builtin { name: "cmmdc", arity: (2, None), args: &[ArgKind::Value], file: None, call: cmmdc },
+    Builtin { name: "prim", arity: (1, Some(1)), args: &[ArgKind::Value], file: None, call: prim },
+    Builtin { name: "lungime", arity: (1, Some(1)), args: &[ArgKind::Value], file: None, call: lungime },
+    Builtin { name: "subșir", arity: (2, Some(3)), args: &[ArgKind::Value], file: None, call: subsir },
+    Builtin { name: "litera", arity: (2, Some(2)), args: &[ArgKind::Value], file: None, call: litera },
+    Builtin { name: "caută", arity: (2, Some(2)), args: &[ArgKind::Value], file: None, call: cauta },
+    Builtin { name: "majuscule", arity: (1, Some(1)), args: &[ArgKind::Value], file: None, call: majuscule },
+    Builtin { name: "minuscule", arity: (1, Some(1)), args: &[ArgKind::Value], file: None, call: minuscule },
+    Builtin { name: "număr", arity: (1, Some(1)), args: &[ArgKind::Value], file: None, call: numar },
+    Builtin { name: "text", arity: (1, Some(1)), args: &[ArgKind::Value], file: None, call: text },
+    Builtin { name: "cod", arity: (1, Some(1)), args: &[ArgKind::Value], file: None, call: cod },
+    Builtin { name: "caracter", arity: (1, Some(1)), args: &[ArgKind::Value], file: None, call: caracter },
+];
+
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|b| b.name == name)
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    lookup(name).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, cell::RefCell};
+
+    use super::lookup;
+    use crate::testing::Shared;
+    use crate::interpreter::{Environment, Object, eval};
+    use crate::ops::Format;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn errors(input: &str) -> Vec<String> {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        parser.parse();
+        parser.errors.iter().map(|e| e.to_string()).collect()
+    }
+
+    fn run(input: &str) -> String {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let root = parser.parse();
+        let envb = Rc::new(RefCell::new(Environment::new()));
+        eval(root, Some(envb)).err().map(|e| e.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn registry() {
+        let citeste = lookup("citește").unwrap();
+        assert!(citeste.assigns() && !lookup("scrie").unwrap().assigns());
+        assert_eq!(citeste.check_arity(0).unwrap_err().to_string(), "Funcția `citește` primește cel puțin 1 argument, nu 0.");
+        assert!(citeste.check_arity(3).is_ok());
+        assert_eq!(run("citește(x + 1)\n"), "Argumentul 1 al funcției `citește` trebuie să fie o variabilă.");
+    }
+
+    fn value(expr: &str) -> Result<Object, String> {
+        let mut parser = Parser::new(Lexer::new(format!("x <- {}\n", expr)));
+        let root = parser.parse();
+        assert!(parser.errors.is_empty(), "{}: {:?}", expr, parser.errors);
+        let envb = Rc::new(RefCell::new(Environment::new()));
+        eval(root, Some(Rc::clone(&envb))).map_err(|e| e.to_string())?;
+        let x = *envb.borrow().syms["x"].clone();
+        Ok(x)
+    }
+
+    fn error(expr: &str) -> String {
+        value(expr).err().unwrap_or_else(|| panic!("`{}` trebuia să dea eroare", expr))
+    }
+
+    #[test]
+    fn math() {
+        let cases = [
+            ("2 ^ 10", Object::Int(1024)), ("2 ^ 3 ^ 2", Object::Int(512)), ("(2 ^ 3) ^ 2", Object::Int(64)),
+            ("2 ^ -1", Object::Float(0.5)), ("2.5 ^ 2", Object::Float(6.25)), ("3²", Object::Int(9)),
+            ("-3 ^ 2", Object::Int(-9)), ("2 × 3 · 4 ÷ 8", Object::Float(3.0)), ("√16 + 1", Object::Float(5.0)),
+            ("|3 - 10|", Object::Int(7)), ("|-2.5|", Object::Float(2.5)), ("max(3, 7.5, -1)", Object::Float(7.5)),
+            ("min(3, 7.5, -1)", Object::Int(-1)), ("abs(-4)", Object::Int(4)), ("sqrt(2.25)", Object::Float(1.5)),
+            ("pow(3, 3)", Object::Int(27)), ("cmmdc(12, -18, 8)", Object::Int(2)), ("prim(97)", Object::Bool(true)),
+            ("prim(91)", Object::Bool(false)), ("prim(1)", Object::Bool(false)),
+            ("prim(9223372036854775783)", Object::Bool(true)), ("prim(3215031751)", Object::Bool(false)),
+            ("prim(4611686014132420609)", Object::Bool(false)), ("prim(2)", Object::Bool(true)), ("prim(37)", Object::Bool(true)),
+        ];
+        for (expr, expected) in cases {
+            assert!(value(expr) == Ok(expected.clone()), "{} = {:?}, nu {}", expr, value(expr).map(|v| v.to_string()), expected);
+        }
+        assert_eq!(error("√(-4)"), "Nu există radical dintr-un număr negativ (-4).");
+        assert_eq!(error("cmmdc(2.5, 5)"), "Funcția `cmmdc` lucrează doar cu numere întregi, nu cu `2.5`.");
+        assert_eq!(error("max('a', 1)"), "Funcția `max` lucrează doar cu numere, nu cu `a`.");
+        assert!(error("10 ^ 30").contains("prea mare"));
+        assert_eq!(error("pow(2)"), "Funcția `pow` primește 2 argumente, nu 1.");
+    }
+
+    #[test]
+    fn strings() {
+        let s = |x: &str| Object::String(x.to_string());
+        let cases = [
+            ("lungime('ștință')", Object::Int(6)), ("subșir('pădure', 1, 3)", s("ădu")), ("subșir('pădure', 3)", s("ure")),
+            ("subșir('ab', 2)", s("")), ("subșir('ab', 0, 10)", s("ab")), ("litera('țară', 0)", s("ț")),
+            ("caută('mărțișor', 'ș')", Object::Int(5)), ("caută('abc', 'x')", Object::Int(-1)),
+            ("majuscule('școală')", s("ȘCOALĂ")), ("minuscule('ÎNAPOI')", s("înapoi")), ("număr(' 42 ')", Object::Int(42)),
+            ("număr('2.5') * 2", Object::Float(5.0)), ("text(12) + text(adevărat)", s("12adevărat")),
+            ("cod('A')", Object::Int(65)), ("cod('ă')", Object::Int(259)), ("caracter(537)", s("ș")),
+        ];
+        for (expr, expected) in cases {
+            assert!(value(expr) == Ok(expected.clone()), "{} = {:?}, nu {}", expr, value(expr).map(|v| v.to_string()), expected);
+        }
+        assert_eq!(error("litera('ab', 2)"), "Poziția 2 este în afara șirului \"ab\", care are 2 caractere.");
+        assert_eq!(error("număr('doi')"), "\"doi\" nu este un număr.");
+        assert_eq!(error("cod('ab')"), "Funcția `cod` primește un singur caracter, nu \"ab\".");
+        assert_eq!(error("lungime(5)"), "Funcția `lungime` lucrează doar cu șiruri de caractere, nu cu `5`.");
+        assert!(error("caracter(-1)").contains("nu este codul"));
+    }
+
+    #[test]
+    fn no_shadowing() {
+        assert_eq!(errors("scrie <- 3\n"), vec!["linia 1: `scrie` este o funcție predefinită și nu poate fi folosită ca variabilă."]);
+        assert_eq!(errors("citește a, citește\n").len(), 1);
+        assert_eq!(errors("pentru scrie <- 1, 3 execută\n■\n").len(), 1);
+        assert!(errors("citește scriere\n").is_empty());
+    }
+
+    fn written(input: &str, format: Format) -> String {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let root = parser.parse();
+        assert!(parser.errors.is_empty(), "{:?}", parser.errors);
+        let out = Shared::default();
+        let envb = Rc::new(RefCell::new(Environment::new()));
+        envb.borrow_mut().output = Box::new(out.clone());
+        envb.borrow_mut().format = format;
+        eval(root, Some(envb)).unwrap_or_else(|e| panic!("{}", e));
+        String::from_utf8(out.0.borrow().clone()).unwrap()
+    }
+
+    #[test]
+    fn precision() {
+        let program = "x <- 0.1 + 0.2\nn <- 3\nscrie x, ' ', x : 2, ' ', 1 / 3 : n, ' ', 5 : 2, ' ', adevărat\n";
+        assert_eq!(written(program, Format::default()), "0.30000000000000004 0.30 0.333 5 adevărat");
+        let format = Format { decimals: Some(1), comma: true, booleans: Some(("DA".to_string(), "NU".to_string())), ..Format::default() };
+        assert_eq!(written(program, format.clone()), "0,3 0,30 0,333 5 DA");
+        assert_eq!(written("x <- număr(text(1.5)) * 2\nscrie text(x), ' ', x\n", format), "3 3,0");
+        assert_eq!(run("scrie 1.5 : -1\n"), "Numărul de zecimale din `: -1` trebuie să fie un număr natural.");
+        assert_eq!(run("scrie 1.5 : 70000\n"), "Numărul de zecimale din `: 70000` depășește maximul de 1000.");
+        assert_eq!(errors("x <- text(1.5 : 2)\n"), vec!["linia 1: Precizia `: n` poate apărea doar în argumentele lui `scrie`."]);
+    }
+}
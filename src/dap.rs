@@ -0,0 +1,461 @@
+use std::{rc::Rc, cell::RefCell, collections::{BTreeSet, HashMap}, io::{BufRead, Cursor, Write}, fs::{canonicalize, read_to_string}, fmt::Display, path::PathBuf};
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::debug::{Mode, Stepper, Stop, evaluate, variables};
+use crate::interpreter::{Environment, Event, Hook, Limits, Object};
+use crate::lexer::Dialect;
+use crate::ops::{Arithmetic, Format};
+use crate::repl::exec;
+use crate::rpc::{read_message, write_message};
+
+/// Singurul fir de execuție raportat clientului.
+const THREAD_ID: i64 = 1;
+const SCOPE_VARIABLES: i64 = 1;
+const SCOPE_EXECUTION: i64 = 2;
+
+pub struct Connection {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    seq: i64,
+}
+
+impl Connection {
+    fn recv(&mut self) -> Result<Option<Value>> {
+        read_message(&mut *self.input)
+    }
+
+    fn send(&mut self, mut message: Value) -> Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        write_message(&mut *self.output, &message)
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) -> Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }))
+    }
+
+    fn fail(&mut self, request: &Value, message: String) -> Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Result<()> {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }))
+    }
+}
+
+type Shared = Rc<RefCell<Connection>>;
+
+/// Trimite ce scrie programul ca evenimente `output`, pentru că stdout este
+/// canalul protocolului.
+struct ProgramOutput(Shared);
+
+impl Write for ProgramOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.borrow_mut().event("output", json!({ "category": "stdout", "output": text }))
+            .map_err(std::io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Clientul a cerut oprirea sesiunii în timpul execuției.
+#[derive(Debug)]
+struct Disconnected;
+
+impl Display for Disconnected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Sesiunea de depanare a fost închisă.")
+    }
+}
+
+impl std::error::Error for Disconnected {}
+
+fn type_name(obj: &Object) -> &'static str {
+    match obj {
+        Object::Int(_) | Object::BigInt(_) => "întreg",
+        Object::Rational(_) => "fracție",
+        Object::Float(_) => "real",
+        Object::Bool(_) => "logic",
+        Object::String(_) => "șir de caractere",
+        Object::Null => "nul",
+        Object::Array(_) => "tablou",
+        _ => "referință",
+    }
+}
+
+fn show(obj: &Object) -> String {
+    match obj {
+        Object::String(x) => format!("{:?}", x),
+        x => x.to_string(),
+    }
+}
+
+/// Un fișier sursă, identificat prin calea canonică atunci când există.
+fn source_key(path: &str) -> PathBuf {
+    canonicalize(path).unwrap_or_else(|_| PathBuf::from(path))
+}
+
+/// Răspunde la `setBreakpoints` și întoarce fișierul și liniile cerute.
+/// Înainte de `launch` (`program` este `None`) toate sunt acceptate; după,
+/// cele din alte fișiere decât programul sunt neverificate.
+fn set_breakpoints(conn: &Shared, program: Option<&PathBuf>, request: &Value) -> Result<(PathBuf, BTreeSet<usize>)> {
+    let args = &request["arguments"];
+    let source = source_key(args["source"]["path"].as_str().unwrap_or_default());
+    let lines: Vec<usize> = args["breakpoints"].as_array()
+        .map(|bps| bps.iter().filter_map(|bp| bp["line"].as_u64()).map(|l| l as usize).collect())
+        .unwrap_or_default();
+    let verified = program.is_none_or(|p| *p == source);
+    let breakpoints: Vec<Value> = lines.iter().map(|l| if verified {
+        json!({ "verified": true, "line": l })
+    } else {
+        json!({ "verified": false, "line": l, "message": "Fișierul nu este programul depanat." })
+    }).collect();
+    conn.borrow_mut().respond(request, json!({ "breakpoints": breakpoints }))?;
+    Ok((source, lines.into_iter().collect()))
+}
+
+struct Session {
+    conn: Shared,
+    stepper: Stepper,
+    path: String,
+    line: usize,
+}
+
+impl Session {
+    /// Răspunde la cererile care nu reiau execuția.
+    fn handle(&mut self, request: &Value, envb: &Rc<RefCell<Environment>>) -> Result<()> {
+        let args = &request["arguments"];
+        let body = match request["command"].as_str().unwrap_or_default() {
+            "threads" => json!({ "threads": [{ "id": THREAD_ID, "name": "program" }] }),
+            "stackTrace" => {
+                let name = std::path::Path::new(&self.path).file_name()
+                    .map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                json!({
+                    "stackFrames": [{
+                        "id": 1,
+                        "name": "program",
+                        "line": self.line,
+                        "column": 1,
+                        "source": { "name": name, "path": self.path },
+                    }],
+                    "totalFrames": 1,
+                })
+            }
+            "scopes" => json!({ "scopes": [
+                { "name": "Variabile", "variablesReference": SCOPE_VARIABLES, "expensive": false },
+                { "name": "Execuție", "variablesReference": SCOPE_EXECUTION, "expensive": false },
+            ] }),
+            "variables" => {
+                let env = envb.borrow();
+                let vars: Vec<Value> = match args["variablesReference"].as_i64() {
+                    Some(SCOPE_VARIABLES) => variables(&env).iter().map(|(name, value)| json!({
+                        "name": name,
+                        "value": show(value),
+                        "type": type_name(value),
+                        "variablesReference": 0,
+                    })).collect(),
+                    Some(SCOPE_EXECUTION) => vec![
+                        json!({ "name": "linia", "value": env.line.to_string(), "variablesReference": 0 }),
+                        json!({ "name": "pași", "value": env.usage.steps.to_string(), "variablesReference": 0 }),
+                        json!({ "name": "nivel", "value": env.nesting.to_string(), "variablesReference": 0 }),
+                    ],
+                    _ => Vec::new(),
+                };
+                json!({ "variables": vars })
+            }
+            "evaluate" => match evaluate(args["expression"].as_str().unwrap_or_default(), envb) {
+                Ok(value) => json!({ "result": show(&value), "type": type_name(&value), "variablesReference": 0 }),
+                Err(e) => return self.conn.borrow_mut().fail(request, e.to_string()),
+            },
+            "setBreakpoints" => {
+                let program = source_key(&self.path);
+                let (source, lines) = set_breakpoints(&self.conn, Some(&program), request)?;
+                if source == program {
+                    self.stepper.breakpoints = lines;
+                }
+                return Ok(());
+            }
+            _ => json!({}),
+        };
+        self.conn.borrow_mut().respond(request, body)
+    }
+
+    /// Execuția este oprită: servește cereri până la una care o reia.
+    fn pause(&mut self, reason: &str, envb: &Rc<RefCell<Environment>>) -> Result<()> {
+        self.conn.borrow_mut().event("stopped", json!({
+            "reason": reason,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        }))?;
+        loop {
+            let request = match self.conn.borrow_mut().recv()? {
+                Some(r) => r,
+                None => return Err(anyhow::Error::new(Disconnected)),
+            };
+            let nesting = envb.borrow().nesting;
+            let mode = match request["command"].as_str().unwrap_or_default() {
+                "continue" => Mode::Continue,
+                "next" => Mode::StepOver(nesting),
+                "stepIn" => Mode::StepInto,
+                "stepOut" => Mode::StepOut(nesting),
+                "disconnect" | "terminate" => {
+                    self.conn.borrow_mut().respond(&request, json!({}))?;
+                    return Err(anyhow::Error::new(Disconnected));
+                }
+                _ => {
+                    self.handle(&request, envb)?;
+                    continue;
+                }
+            };
+            self.stepper.mode = mode;
+            self.conn.borrow_mut().respond(&request, json!({ "allThreadsContinued": true }))?;
+            return Ok(());
+        }
+    }
+}
+
+impl Hook for Session {
+    fn event(&mut self, event: Event, envb: &Rc<RefCell<Environment>>) -> Result<()> {
+        let nesting = envb.borrow().nesting;
+        let stop = self.stepper.check(&event, nesting);
+        if let (Event::Statement(node), Some(stop)) = (event, stop) {
+            let reason = match stop {
+                Stop::Breakpoint => "breakpoint",
+                Stop::Step if self.line == 0 => "entry",
+                Stop::Step => "step",
+            };
+            self.line = node.line;
+            self.pause(reason, envb)?;
+        }
+        Ok(())
+    }
+}
+
+/// Rulează un server DAP: un singur program, scris în `dialect` și lansat prin
+/// cererea `launch` (`program`, opțional `stopOnEntry` și `input`, un fișier
+/// pentru `citește`). Programul rulează cu `limits`, `arithmetic` și `format`, ca în `rolang`.
+pub fn serve(input: Box<dyn BufRead>, output: Box<dyn Write>, limits: Limits, arithmetic: Arithmetic, format: Format, dialect: Dialect) -> Result<()> {
+    let conn: Shared = Rc::new(RefCell::new(Connection { input, output, seq: 0 }));
+    let mut stepper = Stepper::new(Mode::Continue);
+    let mut launch: Option<(String, String, String)> = None;
+    let mut breakpoints: HashMap<PathBuf, BTreeSet<usize>> = HashMap::new();
+    let mut configured = false;
+
+    while launch.is_none() || !configured {
+        let request = match conn.borrow_mut().recv()? {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+        let args = &request["arguments"];
+        match request["command"].as_str().unwrap_or_default() {
+            "initialize" => {
+                conn.borrow_mut().respond(&request, json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsEvaluateForHovers": true,
+                    "supportsTerminateRequest": true,
+                }))?;
+                conn.borrow_mut().event("initialized", json!({}))?;
+            }
+            "launch" => {
+                let path = args["program"].as_str().unwrap_or_default().to_string();
+                let source = match read_to_string(&path) {
+                    Ok(s) => s,
+                    Err(e) => {
+                        conn.borrow_mut().fail(&request, format!("Nu se poate citi `{}`: {}", path, e))?;
+                        continue;
+                    }
+                };
+                let data = match args["input"].as_str() {
+                    Some(file) => match read_to_string(file) {
+                        Ok(s) => s,
+                        Err(e) => {
+                            conn.borrow_mut().fail(&request, format!("Nu se poate citi `{}`: {}", file, e))?;
+                            continue;
+                        }
+                    },
+                    None => String::new(),
+                };
+                if args["stopOnEntry"].as_bool().unwrap_or(false) {
+                    stepper.mode = Mode::StepInto;
+                }
+                launch = Some((path, source, data));
+                conn.borrow_mut().respond(&request, json!({}))?;
+            }
+            "setBreakpoints" => {
+                let program = launch.as_ref().map(|(path, _, _)| source_key(path));
+                let (source, lines) = set_breakpoints(&conn, program.as_ref(), &request)?;
+                breakpoints.insert(source, lines);
+            }
+            "configurationDone" => {
+                configured = true;
+                conn.borrow_mut().respond(&request, json!({}))?;
+            }
+            "threads" => conn.borrow_mut().respond(&request, json!({ "threads": [{ "id": THREAD_ID, "name": "program" }] }))?,
+            "disconnect" | "terminate" => {
+                conn.borrow_mut().respond(&request, json!({}))?;
+                return Ok(());
+            }
+            _ => conn.borrow_mut().respond(&request, json!({}))?,
+        }
+    }
+
+    let (path, source, data) = launch.unwrap();
+    stepper.breakpoints = breakpoints.remove(&source_key(&path)).unwrap_or_default();
+    let envb = Rc::new(RefCell::new(Environment::new()));
+    let mut data = Cursor::new(data);
+    envb.borrow_mut().input = Box::new(move |buf| data.read_line(buf));
+    envb.borrow_mut().output = Box::new(ProgramOutput(Rc::clone(&conn)));
+    envb.borrow_mut().limits = limits;
+    envb.borrow_mut().arithmetic = arithmetic;
+    envb.borrow_mut().format = format;
+    envb.borrow_mut().dialect = dialect;
+    envb.borrow_mut().hook = Some(Box::new(Session { conn: Rc::clone(&conn), stepper, path, line: 0 }));
+
+    let exit_code = match exec(&source, Rc::clone(&envb)) {
+        Ok(_) => 0,
+        Err(e) if e.is::<Disconnected>() => return Ok(()),
+        Err(e) => {
+            conn.borrow_mut().event("output", json!({ "category": "stderr", "output": format!("Eroare ROLang: {}\n", e) }))?;
+            1
+        }
+    };
+    conn.borrow_mut().event("exited", json!({ "exitCode": exit_code }))?;
+    conn.borrow_mut().event("terminated", json!({}))?;
+
+    loop {
+        let request = match conn.borrow_mut().recv()? {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+        conn.borrow_mut().respond(&request, json!({}))?;
+        if matches!(request["command"].as_str(), Some("disconnect" | "terminate")) {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use serde_json::{json, Value};
+
+    use super::serve;
+    use crate::interpreter::Limits;
+    use crate::lexer::Dialect;
+    use crate::ops::{Arithmetic, Format, Integers};
+    use crate::testing::Shared;
+    use crate::rpc::{read_message, write_message};
+
+    /// Un client DAP scriptat: trimite toate cererile și întoarce mesajele primite.
+    fn session(requests: &[Value], limits: Limits, arithmetic: Arithmetic, format: Format) -> Vec<Value> {
+        let mut script = Vec::new();
+        for (seq, request) in requests.iter().enumerate() {
+            let mut request = request.clone();
+            request["seq"] = json!(seq + 1);
+            request["type"] = json!("request");
+            write_message(&mut script, &request).unwrap();
+        }
+        let out = Shared::default();
+        serve(Box::new(Cursor::new(script)), Box::new(out.clone()), limits, arithmetic, format, Dialect::default()).unwrap();
+
+        let bytes = out.0.borrow().clone();
+        let mut reader = Cursor::new(bytes);
+        let mut messages = Vec::new();
+        while let Some(m) = read_message(&mut reader).unwrap() {
+            messages.push(m);
+        }
+        messages
+    }
+
+    fn events<'a>(messages: &'a [Value], name: &str) -> Vec<&'a Value> {
+        messages.iter().filter(|m| m["type"] == "event" && m["event"] == name).collect()
+    }
+
+    fn response<'a>(messages: &'a [Value], command: &str) -> &'a Value {
+        messages.iter().find(|m| m["type"] == "response" && m["command"] == command).unwrap()
+    }
+
+    #[test]
+    fn breakpoint_variables_and_output() {
+        let path = std::env::temp_dir().join(format!("rolang-dap-{}-breakpoint_variables_and_output.ro", std::process::id()));
+        std::fs::write(&path, "a <- 1\nb <- a + 1\nscrie a + b\n").unwrap();
+
+        let messages = session(&[
+            json!({ "command": "initialize", "arguments": { "adapterID": "rolang" } }),
+            json!({ "command": "setBreakpoints", "arguments": { "source": { "path": path.to_str().unwrap() }, "breakpoints": [{ "line": 2 }] } }),
+            json!({ "command": "launch", "arguments": { "program": path.to_str().unwrap() } }),
+            json!({ "command": "setBreakpoints", "arguments": { "source": { "path": "/alt.ro" }, "breakpoints": [{ "line": 1 }] } }),
+            json!({ "command": "configurationDone" }),
+            json!({ "command": "stackTrace", "arguments": { "threadId": 1 } }),
+            json!({ "command": "variables", "arguments": { "variablesReference": 1 } }),
+            json!({ "command": "evaluate", "arguments": { "expression": "a * 10" } }),
+            json!({ "command": "next", "arguments": { "threadId": 1 } }),
+            json!({ "command": "variables", "arguments": { "variablesReference": 1 } }),
+            json!({ "command": "continue", "arguments": { "threadId": 1 } }),
+            json!({ "command": "disconnect" }),
+        ], Limits::default(), Arithmetic::default(), Format::default());
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(events(&messages, "initialized").len(), 1);
+        let answers: Vec<&Value> = messages.iter().filter(|m| m["command"] == "setBreakpoints").collect();
+        assert_eq!(answers[0]["body"]["breakpoints"][0]["verified"], true);
+        assert_eq!(answers[1]["body"]["breakpoints"][0]["verified"], false);
+        let stops = events(&messages, "stopped");
+        assert_eq!(stops.len(), 2);
+        assert_eq!(stops[0]["body"]["reason"], "breakpoint");
+        assert_eq!(stops[1]["body"]["reason"], "step");
+        assert_eq!(response(&messages, "stackTrace")["body"]["stackFrames"][0]["line"], 2);
+        assert_eq!(response(&messages, "evaluate")["body"]["result"], "10");
+
+        let vars: Vec<&Value> = messages.iter().filter(|m| m["command"] == "variables").collect();
+        assert_eq!(vars[0]["body"]["variables"], json!([{ "name": "a", "value": "1", "type": "întreg", "variablesReference": 0 }]));
+        assert_eq!(vars[1]["body"]["variables"].as_array().unwrap().len(), 2);
+
+        assert_eq!(events(&messages, "output")[0]["body"]["output"], "3");
+        assert_eq!(events(&messages, "exited")[0]["body"]["exitCode"], 0);
+        assert_eq!(events(&messages, "terminated").len(), 1);
+        assert_eq!(response(&messages, "disconnect")["success"], true);
+    }
+
+    #[test]
+    fn settings() {
+        let path = std::env::temp_dir().join(format!("rolang-dap-{}-settings.ro", std::process::id()));
+        std::fs::write(&path, "scrie 7 / 2, ' ', 0.5\ncât timp 1 execută\n■\n").unwrap();
+
+        let arithmetic = Arithmetic { integers: Integers::Cpp(32), ..Arithmetic::default() };
+        let format = Format { comma: true, ..Format::default() };
+        let messages = session(&[
+            json!({ "command": "launch", "arguments": { "program": path.to_str().unwrap() } }),
+            json!({ "command": "configurationDone" }),
+            json!({ "command": "disconnect" }),
+        ], Limits { max_steps: Some(100), ..Limits::default() }, arithmetic, format);
+
+        std::fs::remove_file(&path).unwrap();
+        let output: Vec<&Value> = events(&messages, "output");
+        let stdout: String = output.iter().filter(|m| m["body"]["category"] == "stdout").map(|m| m["body"]["output"].as_str().unwrap()).collect();
+        assert_eq!(stdout, "3 0,5");
+        assert!(output.last().unwrap()["body"]["output"].as_str().unwrap().contains("Limita de pași"));
+        assert_eq!(events(&messages, "exited")[0]["body"]["exitCode"], 1);
+    }
+}
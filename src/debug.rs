@@ -0,0 +1,280 @@
+use std::{rc::Rc, cell::RefCell, collections::BTreeSet, io::Write};
+use anyhow::{Result, anyhow};
+
+use crate::interpreter::{Environment, Event, Hook, LineReader, Object, eval};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::repl::exec;
+use crate::trace::source_line;
+
+const HELP: &str = "\
+Comenzi:
+  b N        pune un punct de oprire la linia N
+  d N        șterge punctul de oprire de la linia N
+  s          pas (intră în blocuri)
+  n          pas peste (sare peste blocuri)
+  f          termină blocul curent
+  c          continuă până la următorul punct de oprire
+  p [var]    afișează variabilele sau doar `var`
+  w var      oprește când `var` se schimbă (din nou pentru a anula)
+  e expr     evaluează o expresie în mediul curent
+  l          afișează sursa din jurul liniei curente
+  q          oprește programul
+";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mode {
+    Continue,
+    StepInto,
+    /// Oprește la următoarea instrucțiune de pe un nivel cel mult egal.
+    StepOver(usize),
+    /// Oprește la următoarea instrucțiune de pe un nivel mai mic.
+    StepOut(usize),
+}
+
+impl Mode {
+    pub fn should_stop(&self, nesting: usize) -> bool {
+        match *self {
+            Mode::Continue => false,
+            Mode::StepInto => true,
+            Mode::StepOver(level) => nesting <= level,
+            Mode::StepOut(level) => nesting < level,
+        }
+    }
+}
+
+/// Evaluează o expresie (sau instrucțiune) în mediul dat.
+pub fn evaluate(expr: &str, envb: &Rc<RefCell<Environment>>) -> Result<Object> {
+    let dialect = envb.borrow().dialect.clone();
+    let mut parser = Parser::new(Lexer::with_dialect(format!("{}\n", expr), dialect));
+    let root = parser.parse();
+    if !parser.errors.is_empty() {
+        let errors: Vec<_> = parser.errors.iter().map(|e| e.message.clone()).collect();
+        return Err(anyhow!("{}", errors.join("; ")));
+    }
+    eval(root, Some(Rc::clone(envb)))
+}
+
+/// Variabilele din mediu, sortate după nume.
+pub fn variables(env: &Environment) -> Vec<(String, Object)> {
+    let mut vars: Vec<_> = env.syms.iter().map(|(k, v)| (k.clone(), *v.clone())).collect();
+    vars.sort_by(|a, b| a.0.cmp(&b.0));
+    vars
+}
+
+/// Decide înaintea căror instrucțiuni se oprește execuția: puncte de
+/// oprire și pași. Folosit de depanator și de serverul DAP.
+pub struct Stepper {
+    pub breakpoints: BTreeSet<usize>,
+    pub mode: Mode,
+    /// Linia ultimului eveniment; o linie cu mai multe instrucțiuni
+    /// oprește o singură dată la un punct de oprire.
+    prev_line: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Stop {
+    Step,
+    Breakpoint,
+}
+
+impl Stepper {
+    pub fn new(mode: Mode) -> Stepper {
+        Stepper {
+            breakpoints: BTreeSet::new(),
+            mode,
+            prev_line: 0,
+        }
+    }
+
+    /// Întoarce motivul opririi dacă execuția trebuie oprită la acest eveniment.
+    pub fn check(&mut self, event: &Event, nesting: usize) -> Option<Stop> {
+        match event {
+            Event::Statement(node) => {
+                let at_breakpoint = self.breakpoints.contains(&node.line) && node.line != self.prev_line;
+                self.prev_line = node.line;
+                if at_breakpoint {
+                    Some(Stop::Breakpoint)
+                } else if self.mode.should_stop(nesting) {
+                    Some(Stop::Step)
+                } else {
+                    None
+                }
+            }
+            Event::LoopTest(node, _) => {
+                self.prev_line = node.line;
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+pub struct Debugger {
+    lines: Vec<String>,
+    stepper: Stepper,
+    watches: Vec<(String, Option<Object>)>,
+    /// Linia la care s-a oprit ultima oară.
+    line: usize,
+    input: LineReader,
+    output: Box<dyn Write>,
+}
+
+impl Debugger {
+    pub fn new(source: &str, input: LineReader, output: Box<dyn Write>) -> Debugger {
+        Debugger {
+            lines: source.lines().map(|l| l.to_string()).collect(),
+            stepper: Stepper::new(Mode::StepInto),
+            watches: Vec::new(),
+            line: 0,
+            input,
+            output,
+        }
+    }
+
+    fn changed_watches(&mut self, env: &Environment) -> Vec<String> {
+        let mut changes = Vec::new();
+        for (name, old) in self.watches.iter_mut() {
+            let new = env.syms.get(name).map(|v| *v.clone());
+            if new != *old {
+                let show = |v: &Option<Object>| v.as_ref().map(|x| x.to_string()).unwrap_or("-".to_string());
+                changes.push(format!("{}: {} → {}", name, show(old), show(&new)));
+                *old = new;
+            }
+        }
+        changes
+    }
+
+    fn print_var(&mut self, name: &str, env: &Environment) -> Result<()> {
+        match env.syms.get(name) {
+            Some(v) => writeln!(self.output, "{} = {}", name, v)?,
+            None => writeln!(self.output, "{} nu are valoare", name)?,
+        }
+        Ok(())
+    }
+
+    fn list(&mut self) -> Result<()> {
+        let from = self.line.saturating_sub(3).max(1);
+        for n in from..=(self.line + 3).min(self.lines.len()) {
+            let mark = if n == self.line { "→" } else if self.stepper.breakpoints.contains(&n) { "●" } else { " " };
+            writeln!(self.output, "{} {:4} {}", mark, n, self.lines[n - 1])?;
+        }
+        Ok(())
+    }
+
+    /// Citește comenzi până la una care reia execuția.
+    fn prompt(&mut self, envb: &Rc<RefCell<Environment>>) -> Result<()> {
+        loop {
+            write!(self.output, "(dbg) ")?;
+            self.output.flush()?;
+            let mut cmd = String::new();
+            if (self.input)(&mut cmd)? == 0 {
+                self.stepper.mode = Mode::Continue;
+                return Ok(());
+            }
+            let cmd = cmd.trim();
+            let (name, arg) = match cmd.split_once(char::is_whitespace) {
+                Some((name, arg)) => (name, arg.trim()),
+                None => (cmd, ""),
+            };
+            let nesting = envb.borrow().nesting;
+            match name {
+                "s" | "pas" => { self.stepper.mode = Mode::StepInto; return Ok(()); }
+                "n" | "peste" => { self.stepper.mode = Mode::StepOver(nesting); return Ok(()); }
+                "f" | "ieși" => { self.stepper.mode = Mode::StepOut(nesting); return Ok(()); }
+                "c" | "continuă" => { self.stepper.mode = Mode::Continue; return Ok(()); }
+                "q" | "gata" => return Err(anyhow!("Depanare oprită.")),
+                "b" | "d" => match arg.parse::<usize>() {
+                    Ok(n) if name == "b" => { self.stepper.breakpoints.insert(n); }
+                    Ok(n) => { self.stepper.breakpoints.remove(&n); }
+                    Err(_) => writeln!(self.output, "Se aștepta un număr de linie.")?,
+                },
+                "p" if arg.is_empty() => {
+                    for (name, value) in variables(&envb.borrow()) {
+                        writeln!(self.output, "{} = {}", name, value)?;
+                    }
+                }
+                "p" => self.print_var(&arg.to_lowercase(), &envb.borrow())?,
+                "w" => {
+                    let name = arg.to_lowercase();
+                    if let Some(pos) = self.watches.iter().position(|(n, _)| *n == name) {
+                        self.watches.remove(pos);
+                    } else {
+                        let current = envb.borrow().syms.get(&name).map(|v| *v.clone());
+                        self.watches.push((name, current));
+                    }
+                }
+                "e" => match evaluate(arg, envb) {
+                    Ok(v) => writeln!(self.output, "{}", v)?,
+                    Err(e) => writeln!(self.output, "Eroare: {}", e)?,
+                },
+                "l" => self.list()?,
+                "" => (),
+                _ => write!(self.output, "{}", HELP)?,
+            }
+        }
+    }
+}
+
+impl Hook for Debugger {
+    fn event(&mut self, event: Event, envb: &Rc<RefCell<Environment>>) -> Result<()> {
+        let nesting = envb.borrow().nesting;
+        let stop = self.stepper.check(&event, nesting);
+        let node = match event {
+            Event::Statement(node) => node,
+            _ => return Ok(()),
+        };
+        let changes = self.changed_watches(&envb.borrow());
+        if changes.is_empty() && stop.is_none() {
+            return Ok(());
+        }
+
+        self.line = node.line;
+        for change in changes {
+            writeln!(self.output, "schimbare {}", change)?;
+        }
+        writeln!(self.output, "linia {}: {}", node.line, source_line(&self.lines, node.line))?;
+        self.prompt(envb)
+    }
+}
+
+/// Rulează programul sub depanator, cu comenzile citite din `input`.
+pub fn debug(source: &str, envb: Rc<RefCell<Environment>>, input: LineReader, output: Box<dyn Write>) -> Result<Object> {
+    envb.borrow_mut().hook = Some(Box::new(Debugger::new(source, input, output)));
+    exec(source, envb)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, cell::RefCell, io::{BufRead, Cursor}};
+
+    use super::debug;
+    use crate::interpreter::Environment;
+    use crate::testing::Shared;
+
+    fn run(source: &str, commands: &str) -> String {
+        let out = Shared::default();
+        let envb = Rc::new(RefCell::new(Environment::new()));
+        envb.borrow_mut().output = Box::new(std::io::sink());
+        let mut commands = Cursor::new(commands.to_string());
+        let res = debug(source, envb, Box::new(move |buf| commands.read_line(buf)), Box::new(out.clone()));
+        assert!(res.is_ok());
+        String::from_utf8(out.0.borrow().clone()).unwrap()
+    }
+
+    #[test]
+    fn breakpoints_and_eval() {
+        let source = "a <- 1\nb <- 2\nc <- a + b\n";
+        let out = run(source, "b 3\nc\np\ne c * 10\nn\ne c * 10\n");
+        assert_eq!(out, "linia 1: a <- 1\n(dbg) (dbg) linia 3: c <- a + b\n(dbg) a = 1\nb = 2\n(dbg) Eroare: Operația `*` nu are sens între `nul` și un număr întreg.\n(dbg) ");
+    }
+
+    #[test]
+    fn step_over_and_watch() {
+        let source = "s <- 0\npentru i <- 1, 3 execută\n  s <- s + i\n■\nscrie s\n";
+        let out = run(source, "n\ns\nw s\nc\nw s\nb 3\nc\nf\nc\n");
+        assert_eq!(out, "linia 1: s <- 0\n(dbg) linia 2: pentru i <- 1, 3 execută\n(dbg) linia 3: s <- s + i\n(dbg) (dbg) \
+                         schimbare s: 0 → 1\nlinia 3: s <- s + i\n(dbg) (dbg) (dbg) \
+                         linia 3: s <- s + i\n(dbg) linia 5: scrie s\n(dbg) ");
+    }
+}
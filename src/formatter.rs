@@ -0,0 +1,361 @@
+use anyhow::{Result, anyhow};
+
+use crate::builtins::lookup;
+use crate::lexer::{Dialect, Lexer, Token};
+use crate::parser::{ASTNode, ASTNodeValue, Parser};
+
+const INDENT: &str = "    ";
+
+/// Nivelul de precedență al unui nod, la fel ca în parser: 1 pentru
+/// operatorii logici și de comparație, 2 pentru adunare, 3 pentru înmulțire,
+/// 4 pentru operatorii unari, 5 pentru putere și `√` și 6 pentru restul.
+fn level(node: &ASTNode) -> u8 {
+    match &node.value {
+        ASTNodeValue::And | ASTNodeValue::Or |
+        ASTNodeValue::Equal | ASTNodeValue::NotEqual |
+        ASTNodeValue::LessThan | ASTNodeValue::GreaterThan |
+        ASTNodeValue::LessThanEqual | ASTNodeValue::GreaterThanEqual => 1,
+        ASTNodeValue::Add => 2,
+        ASTNodeValue::Subtract if node.children.len() == 2 => 2,
+        ASTNodeValue::Multiply | ASTNodeValue::Divide | ASTNodeValue::Mod => 3,
+        ASTNodeValue::Subtract | ASTNodeValue::Not => 4,
+        ASTNodeValue::Power => 5,
+        ASTNodeValue::FunctionCall(name) if name == "sqrt" && node.children.len() == 1 => 5,
+        _ => 6,
+    }
+}
+
+fn operator(value: &ASTNodeValue) -> Option<&'static str> {
+    Some(match value {
+        ASTNodeValue::And => "și",
+        ASTNodeValue::Or => "sau",
+        ASTNodeValue::Equal => "=",
+        ASTNodeValue::NotEqual => "≠",
+        ASTNodeValue::LessThan => "<",
+        ASTNodeValue::GreaterThan => ">",
+        ASTNodeValue::LessThanEqual => "≤",
+        ASTNodeValue::GreaterThanEqual => "≥",
+        ASTNodeValue::Add => "+",
+        ASTNodeValue::Subtract => "-",
+        ASTNodeValue::Multiply => "*",
+        ASTNodeValue::Divide => "/",
+        ASTNodeValue::Mod => "%",
+        ASTNodeValue::Power => "^",
+        _ => return None,
+    })
+}
+
+fn string(s: &str) -> String {
+    let quote = if s.contains('"') { '\'' } else { '"' };
+    let escaped = s.replace('\n', "\\n").replace('\r', "\\r").replace('\t', "\\t").replace('\x1b', "\\e");
+    format!("{}{}{}", quote, escaped, quote)
+}
+
+fn parens(s: String, needed: bool) -> String {
+    if needed { format!("({})", s) } else { s }
+}
+
+/// Copilul `i` al unui nod, ca expresie; arborii cu erori de sintaxă pot avea
+/// mai puțini copii.
+fn arg(node: &ASTNode, i: usize) -> String {
+    node.children.get(i).map_or_else(String::new, |c| expr(c))
+}
+
+/// Un nod care nu este expresie, de exemplu un `dacă` din antetul unui `cât
+/// timp`, se scrie ca instrucțiune; nodurile invalide nu se scriu.
+fn nested(node: &ASTNode) -> String {
+    let mut out = String::new();
+    match node.value {
+        ASTNodeValue::Program => block(&mut out, node, 0),
+        ASTNodeValue::If | ASTNodeValue::For | ASTNodeValue::While | ASTNodeValue::Until | ASTNodeValue::Execute => stmt(&mut out, node, 0),
+        _ => (),
+    }
+    out.trim_end().to_string()
+}
+
+/// Scrie o expresie cu parantezele minime pentru care parserul reconstruiește
+/// același arbore.
+pub fn expr(node: &ASTNode) -> String {
+    let lvl = level(node);
+    match &node.value {
+        ASTNodeValue::Identifier(x) => x.clone(),
+        ASTNodeValue::Int(x) => x.to_string(),
+        ASTNodeValue::BigInt(x) => x.to_string(),
+        ASTNodeValue::Float(x) => {
+            let s = x.to_string();
+            if s.contains('.') { s } else { format!("{}.0", s) }
+        }
+        ASTNodeValue::String(x) => string(x),
+        ASTNodeValue::Bool(true) => "adevărat".to_string(),
+        ASTNodeValue::Bool(false) => "fals".to_string(),
+        ASTNodeValue::Null => "nul".to_string(),
+        ASTNodeValue::Break(x) | ASTNodeValue::Continue(x) => x.clone(),
+        ASTNodeValue::Cast(x, t) => format!("{} ({})", x, t),
+        ASTNodeValue::File(x) => string(x),
+        ASTNodeValue::Decimals => format!("{} : {}", arg(node, 0), arg(node, 1)),
+        ASTNodeValue::Set => {
+            let (targets, values) = node.assignment();
+            let list = |nodes: &[Box<ASTNode>]| nodes.iter().map(|n| expr(n)).collect::<Vec<_>>().join(", ");
+            format!("{} ← {}", list(targets), list(values))
+        }
+        ASTNodeValue::Swap => format!("{} ↔ {}", arg(node, 0), arg(node, 1)),
+        ASTNodeValue::Floor => format!("[{}]", arg(node, 0)),
+        ASTNodeValue::Index => format!("{}[{}]", arg(node, 0), arg(node, 1)),
+        ASTNodeValue::FunctionCall(_) if lvl == 5 => {
+            let operand = &node.children[0];
+            format!("√{}", parens(expr(operand), level(operand) < 5))
+        }
+        ASTNodeValue::FunctionCall(name) if name == "abs" && node.children.len() == 1 => {
+            format!("|{}|", arg(node, 0))
+        }
+        ASTNodeValue::FunctionCall(name) => {
+            let args: Vec<_> = node.children.iter().map(|c| expr(c)).collect();
+            format!("{}({})", name, args.join(", "))
+        }
+        ASTNodeValue::Subtract | ASTNodeValue::Not if lvl == 4 => {
+            let Some(operand) = node.children.first() else { return String::new() };
+            let op = if node.value == ASTNodeValue::Not { "not " } else { "-" };
+            format!("{}{}", op, parens(expr(operand), level(operand) < 3))
+        }
+        value => {
+            let (Some(op), [left, right]) = (operator(value), node.children.as_slice()) else {
+                return nested(node);
+            };
+            // Adunarea, puterea și operatorii logici sunt asociativi la
+            // dreapta în parser, înmulțirea la stânga, cu o putere în dreapta.
+            let (left_parens, right_parens) = if lvl == 5 {
+                (level(left) < 6, level(right) < 5)
+            } else if lvl == 3 {
+                (level(left) < 3 || level(left) == 4, level(right) < 5)
+            } else {
+                (level(left) <= lvl, level(right) < lvl)
+            };
+            format!("{} {} {}", parens(expr(left), left_parens), op, parens(expr(right), right_parens))
+        }
+    }
+}
+
+/// Un apel la nivel de instrucțiune se scrie fără paranteze (`scrie a, b`)
+/// când parserul îl recunoaște așa: primul argument începe cu un nume sau o
+/// valoare.
+fn call_stmt(name: &str, node: &ASTNode) -> String {
+    let builtin = lookup(name);
+    let assigns = builtin.is_some_and(|b| b.assigns());
+    if let Some(file) = node.children.first() && let ASTNodeValue::File(path) = &file.value
+        && let Some((keyword, _)) = builtin.and_then(|b| b.file) {
+        let mut rest = node.clone();
+        rest.children.remove(0);
+        let args = if assigns { read_args(&rest) } else { rest.children.iter().map(|c| expr(c)).collect() };
+        return format!("{} {} {} {}", name, keyword, string(path), args.join(", ")).trim_end().to_string();
+    }
+    let args = if assigns { read_args(node) } else { node.children.iter().map(|c| expr(c)).collect() };
+    let plain = match args.first() {
+        Some(first) => matches!(Lexer::new(first.clone()).next(),
+            Ok(Token::Identifier(_) | Token::Int(_) | Token::BigInt(_) | Token::Float(_) | Token::String(_) | Token::True | Token::False | Token::Null |
+               Token::Sqrt | Token::Pipe)),
+        None => false,
+    };
+    if plain {
+        format!("{} {}", name, args.join(", "))
+    } else {
+        format!("{}({})", name, args.join(", "))
+    }
+}
+
+/// Argumentele lui `citește`, cu variabilele consecutive de același tip
+/// grupate sub o singură adnotare: `a, b (numere naturale)`.
+fn read_args(node: &ASTNode) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+    let mut group: Vec<&str> = Vec::new();
+    for (i, child) in node.children.iter().enumerate() {
+        let t = match &child.value {
+            ASTNodeValue::Cast(x, t) => {
+                group.push(x);
+                t
+            }
+            _ => {
+                args.push(expr(child));
+                continue;
+            }
+        };
+        let next = node.children.get(i + 1).map(|n| &n.value);
+        if !matches!(next, Some(ASTNodeValue::Cast(_, u)) if u == t) {
+            let annotation = if group.len() > 1 { t.plural().to_string() } else { t.to_string() };
+            args.push(format!("{} ({})", group.join(", "), annotation));
+            group.clear();
+        }
+    }
+    args
+}
+
+fn for_header(node: &ASTNode) -> String {
+    let args: Vec<_> = node.children.iter().take(3).filter(|c| c.value != ASTNodeValue::Program).map(|c| expr(c)).collect();
+    format!("pentru {}", args.join(", "))
+}
+
+fn block(out: &mut String, node: &ASTNode, depth: usize) {
+    for child in &node.children {
+        stmt(out, child, depth);
+    }
+}
+
+fn stmt(out: &mut String, node: &ASTNode, depth: usize) {
+    let indent = INDENT.repeat(depth);
+    match &node.value {
+        ASTNodeValue::If => {
+            let mut keyword = "dacă";
+            let mut node = node;
+            loop {
+                let branches = node.children.chunks_exact(2);
+                let other = branches.remainder().first();
+                for branch in branches {
+                    out.push_str(&format!("{}{} {} atunci\n", indent, keyword, expr(&branch[0])));
+                    block(out, &branch[1], depth + 1);
+                    keyword = "altfel dacă";
+                }
+                let Some(other) = other else { break };
+                // Un `altfel` care conține doar un alt `dacă` devine `altfel dacă`.
+                if let [inner] = other.children.as_slice() && inner.value == ASTNodeValue::If {
+                    node = inner;
+                    continue;
+                }
+                out.push_str(&format!("{}altfel\n", indent));
+                block(out, other, depth + 1);
+                break;
+            }
+            out.push_str(&format!("{}■\n", indent));
+        }
+        ASTNodeValue::For | ASTNodeValue::While | ASTNodeValue::Until => {
+            let header = match node.value {
+                ASTNodeValue::For => for_header(node),
+                ASTNodeValue::While => format!("cât timp {}", arg(node, 0)),
+                _ => format!("până când {}", arg(node, 0)),
+            };
+            out.push_str(&format!("{}{} execută\n", indent, header));
+            if let Some(body) = node.children.last() && body.value == ASTNodeValue::Program {
+                block(out, body, depth + 1);
+            }
+            out.push_str(&format!("{}■\n", indent));
+        }
+        ASTNodeValue::Execute => {
+            out.push_str(&format!("{}execută\n", indent));
+            if let Some(body) = node.children.first() {
+                block(out, body, depth + 1);
+            }
+            match node.children.get(1) {
+                Some(header) => {
+                    let header = match header.value {
+                        ASTNodeValue::For => for_header(header),
+                        ASTNodeValue::While => format!("cât timp {}", arg(header, 0)),
+                        _ => format!("până când {}", arg(header, 0)),
+                    };
+                    out.push_str(&format!("{}{}\n", indent, header));
+                }
+                None => out.push_str(&format!("{}■\n", indent)),
+            }
+        }
+        ASTNodeValue::FunctionCall(name) => out.push_str(&format!("{}{}\n", indent, call_stmt(name, node))),
+        _ => out.push_str(&format!("{}{}\n", indent, expr(node))),
+    }
+}
+
+/// Rescrie programul în forma standard: câte o instrucțiune pe linie, blocuri
+/// indentate și închise cu `■`.
+pub fn format(root: &ASTNode) -> String {
+    let mut out = String::new();
+    block(&mut out, root, 0);
+    out
+}
+
+/// Formatează o sursă scrisă în `dialect`; refuză sursele cu erori de sintaxă.
+pub fn format_source(input: &str, dialect: Dialect) -> Result<String> {
+    let mut parser = Parser::new(Lexer::with_dialect(input.to_string(), dialect));
+    let root = parser.parse();
+    if let Some(err) = parser.errors.first() {
+        return Err(anyhow!("Sursa are erori ({}).", err));
+    }
+    Ok(format(&root))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::{expr, format, format_source};
+    use crate::lexer::{Dialect, Lexer};
+    use crate::parser::Parser;
+    use crate::ops::Arithmetic;
+    use crate::types::infer_with;
+
+    fn format_default(input: &str) -> Result<String> {
+        format_source(input, Dialect::default())
+    }
+
+    #[test]
+    fn format_program() {
+        let input = "citește x (număr natural)\np<-1; m←-1\n┌cât timp p≤x execută\n│ c<-[x/p]%10\n│┌dacă c>m atunci\n││ m<-c; p<-p*10\n││altfel\n││ x<-[x/(p*10)]*p+x%p\n│└■\n└■\nscrie(-a*2, 'x')\n";
+        let formatted = format_default(input).unwrap();
+        assert_eq!(formatted, "citește x (număr natural)\np ← 1\nm ← -1\ncât timp p ≤ x execută\n    c ← [x / p] % 10\n    \
+                               dacă c > m atunci\n        m ← c\n        p ← p * 10\n    altfel\n        x ← [x / (p * 10)] * p + x % p\n    ■\n■\n\
+                               scrie(-a * 2, \"x\")\n");
+        assert_eq!(format_default(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn format_keeps_tree() {
+        for input in ["x <- a - (b - c) - d\n", "x <- -a * b\n", "x <- (-a) * b / (c * d)\n", "x <- not a = b sau c\n",
+                      "execută\n  i <- i + 1\npână când i > 3\n", "citește din 'a.in' n (număr natural)\nscrie în 'a.out' n * 2, ' '\n", "pentru i <- 1, 10, 2 execută\nscrie i\n■\n", "x <- 2.0 + 0.5\n",
+                      "cât timp a execută\ndacă b atunci întrerupe\n■\ncontinuă\n■\n", "a, b <- b, a + b\na <-> b\n",
+                      "v[i] <-> v[j + 1]\ni, a[i][2] <- i + 1, a[i - 1][2] * 2\n"] {
+            let once = format_default(input).unwrap();
+            assert_eq!(format_default(&once).unwrap(), once, "{}", input);
+        }
+        for input in ["x <- √(a + b) * |c - d| ^ 2\n", "x <- 2 ^ 3 ^ 2\n", "x <- (2 ^ 3) ^ 2 + (√a) ^ 2\n", "x <- -a² × b ÷ 2 ^ -c\n"] {
+            let once = format_default(input).unwrap();
+            assert_eq!(format_default(&once).unwrap(), once, "{}", input);
+        }
+        assert_eq!(format_default("x <- √(a+b) · |c-d|² ÷ sqrt(e)\n").unwrap(), "x ← √(a + b) * |c - d| ^ 2 / √e\n");
+        assert_eq!(format_default("x <- (2^3)^2 + 2^(3^2)\n").unwrap(), "x ← (2 ^ 3) ^ 2 + 2 ^ 3 ^ 2\n");
+        assert_eq!(format_default("x <- a - (b - c) - d\n").unwrap(), "x ← a - (b - c) - d\n");
+        assert_eq!(format_default("x <- (a - b) - c\n").unwrap(), "x ← (a - b) - c\n");
+        assert!(format_default("x <- (\n").is_err());
+        assert_eq!(format_default("citește din 'a.in' a, b\nscrie în 'a.out' a+b\n").unwrap(), "citește din \"a.in\" a, b\nscrie în \"a.out\" a + b\n");
+        assert_eq!(format_default("citește a, b (numere naturale), c (ref), s\n").unwrap(), "citește a, b (numere naturale), c (număr întreg), s\n");
+    }
+
+    #[test]
+    fn format_keeps_dialect() {
+        let dialect = Dialect { breaks: vec!["break".into()], continues: vec!["continue".into()] };
+        let formatted = format_source("cât timp a execută\n  dacă b atunci break\n  ■\n  continue\n■\n", dialect.clone()).unwrap();
+        assert_eq!(formatted, "cât timp a execută\n    dacă b atunci\n        break\n    ■\n    continue\n■\n");
+        assert_eq!(format_source(&formatted, dialect).unwrap(), formatted);
+        assert_eq!(format_default("cât timp a execută\nîntrerupe\n■\n").unwrap(), "cât timp a execută\n    întrerupe\n■\n");
+    }
+
+    #[test]
+    fn format_else_if() {
+        let chained = "dacă a = 1 atunci\n    scrie 1\naltfel dacă a = 2 atunci\n    scrie 2\naltfel\n    scrie 3\n■\n";
+        assert_eq!(format_default(chained).unwrap(), chained);
+        let nested = "dacă a = 1 atunci\n scrie 1\naltfel\n dacă a = 2 atunci\n  scrie 2\n altfel\n  scrie 3\n ■\n■\n";
+        assert_eq!(format_default(nested).unwrap(), chained);
+        let kept = "dacă a = 1 atunci\n    scrie 1\naltfel\n    scrie 0\n    dacă a = 2 atunci\n        scrie 2\n    ■\n■\n";
+        assert_eq!(format_default(kept).unwrap(), kept);
+    }
+
+    #[test]
+    fn malformed_trees() {
+        // Arborii cu erori de sintaxă ajung la formatare și inferență prin
+        // serverul de limbaj (hover, diagnostice).
+        for input in ["% <-> execută\n", "din repetă\n", "și [ <-> repetă pentru\n", "scrie (\n", "x ² nul execută x pentru ↔ ^ (\n",
+                      "cât timp dacă a atunci b ■ execută\n■\n", "repetă √ atunci : 1 ] ■\n"] {
+            let mut parser = Parser::new(Lexer::new(input.to_string()));
+            let root = parser.parse();
+            format(&root);
+            for child in &root.children {
+                expr(child);
+            }
+            infer_with(&root, Arithmetic::default());
+            let _ = format_default(input);
+        }
+    }
+}
@@ -1,40 +1,38 @@
-use std::{collections::HashMap, fmt::Display, cell::RefCell, rc::Rc, io::{Write, stdin, BufRead}, cmp::Ordering};
-use text_io::read;
+use std::{collections::{BTreeMap, HashMap}, fmt::Display, cell::RefCell, rc::Rc, io::{Write, BufRead, BufReader, stdin, stdout}, fs::File, mem, time::{Duration, Instant}, sync::{Arc, atomic::{AtomicBool, Ordering as AtomicOrdering}}};
 use anyhow::{Result, anyhow};
+use num_bigint::{BigInt, Sign};
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, ToPrimitive};
 
 use crate::parser::{ASTNode, ASTNodeValue};
+use crate::builtins::{self, Arg, ArgKind, Builtin, Stream};
+use crate::ops::{self, Arithmetic, BinOp, Format, Integers};
+use crate::lexer::{Dialect, VarType};
+use crate::types::Type;
 
 #[derive(Clone, PartialEq)]
 pub enum Object {
     Int(i64),
+    /// Un număr întreg care nu încape în `i64`, doar cu `--bigint`.
+    BigInt(BigInt),
+    /// O fracție care nu se simplifică la un întreg, doar cu `--exact`.
+    Rational(BigRational),
     Float(f64),
     Bool(bool),
     String(String),
     Null,
-    CastUnsignedRef(String),
-    CastFloatRef(String),
-    CastRef(String),
-}
-
-impl PartialOrd for Object {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self, other) {
-            (Object::Int(a), Object::Int(b)) => a.partial_cmp(b),
-            (Object::Float(a), Object::Float(b)) => a.partial_cmp(b),
-            (Object::Int(a), Object::Float(b)) => (*a as f64).partial_cmp(b),
-            (Object::Float(a), Object::Int(b)) => a.partial_cmp(&(*b as f64)),
-            (Object::Bool(a), Object::Bool(b)) => a.partial_cmp(b),
-            (Object::String(a), Object::String(b)) => a.partial_cmp(b),
-            (Object::Null, Object::Null) => Some(Ordering::Equal),
-            _ => None,
-        }
-    }
+    Cast(String, VarType),
+    /// Un tablou, creat la prima atribuire a unui element: `v[1] ← 5`.
+    /// Elementele neatribuite sunt `nul`.
+    Array(BTreeMap<i64, Object>),
 }
 
 impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Object::Int(x) => write!(f, "{}", x),
+            Object::BigInt(x) => write!(f, "{}", x),
+            Object::Rational(x) => write!(f, "{}", x),
             Object::Float(x) => write!(f, "{}", x),
             Object::Bool(x) => write!(f, "{}", match x {
                 true => "adevărat",
@@ -42,26 +40,443 @@ impl Display for Object {
             }),
             Object::Null => write!(f, "nul"),
             Object::String(x) => write!(f, "{}", x),
-            Object::CastUnsignedRef(x) => write!(f, "RefNat({})", x),
-            Object::CastFloatRef(x) => write!(f, "RefFloat({})", x),
-            Object::CastRef(x) => write!(f, "Ref({})", x),
+            Object::Cast(x, t) => write!(f, "{} ({})", x, t),
+            Object::Array(items) => {
+                let items: Vec<_> = items.iter().map(|(i, x)| format!("{}: {}", i, x)).collect();
+                write!(f, "{{{}}}", items.join(", "))
+            }
         }
     }
 }
 
+/// Limitele unei execuții. `None` înseamnă fără limită.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    /// Numărul maxim de instrucțiuni executate (inclusiv testele buclelor).
+    pub max_steps: Option<u64>,
+    /// Timpul maxim de execuție.
+    pub timeout: Option<Duration>,
+    /// Numărul maxim de blocuri de instrucțiuni imbricate (1 pentru
+    /// programul principal).
+    pub max_depth: Option<usize>,
+}
+
+/// Cât s-a consumat din fiecare limită.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Usage {
+    pub steps: u64,
+    pub elapsed: Duration,
+    pub max_depth: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Limit {
+    Steps,
+    Time,
+    Depth,
+}
+
+/// Eroarea întoarsă de `eval` când o limită din `Limits` a fost depășită.
+#[derive(Debug)]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    pub line: usize,
+    pub usage: Usage,
+    pub limits: Limits,
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Steps => write!(f, "pași"),
+            Limit::Time => write!(f, "timp"),
+            Limit::Depth => write!(f, "adâncime"),
+        }
+    }
+}
+
+fn show_limit<T: Display>(limit: Option<T>) -> String {
+    match limit {
+        Some(x) => x.to_string(),
+        None => "∞".to_string(),
+    }
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Limita de {} a fost depășită la linia {} (pași: {}/{}, timp: {} ms/{}, adâncime: {}/{})",
+            self.limit, self.line,
+            self.usage.steps, show_limit(self.limits.max_steps),
+            self.usage.elapsed.as_millis(), show_limit(self.limits.timeout.map(|t| format!("{} ms", t.as_millis()))),
+            self.usage.max_depth, show_limit(self.limits.max_depth))
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// Eroarea întoarsă de `eval` când execuția a fost oprită prin `Environment::interrupt`.
+#[derive(Debug)]
+pub struct Interrupted {
+    pub line: usize,
+}
+
+impl Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "întrerupt la linia {}", self.line)
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Evenimentele pe care `eval` le trimite către `Hook`.
+pub enum Event<'a> {
+    /// O instrucțiune dintr-un bloc urmează să fie executată.
+    Statement(&'a ASTNode),
+    /// O atribuire a fost executată.
+    Set(&'a ASTNode),
+    /// Un apel care atribuie variabile, ca `citește`, a fost executat.
+    Read(&'a ASTNode),
+    /// Condiția unei bucle a fost evaluată, cu rezultatul ei (`adevărat`
+    /// înseamnă că bucla continuă).
+    LoopTest(&'a ASTNode, bool),
+}
+
+/// Un `întrerupe` sau `continuă` executat: blocurile se opresc până la bucla
+/// cea mai apropiată, care îl preia.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Control {
+    Break,
+    Continue,
+}
+
+/// Observă execuția. Cât timp rulează, hook-ul este scos din `Environment`,
+/// deci poate evalua expresii în mediul primit fără să fie apelat din nou.
+pub trait Hook {
+    fn event(&mut self, event: Event, envb: &Rc<RefCell<Environment>>) -> Result<()>;
+}
+
+/// Sursa datelor de intrare: citește o linie, ca `BufRead::read_line`. Nu
+/// folosim un `BufRead` peste stdin pentru că ar citi în avans și comenzile
+/// destinate depanatorului.
+pub type LineReader = Box<dyn FnMut(&mut String) -> std::io::Result<usize>>;
+
 pub struct Environment {
     pub syms: HashMap<String, Box<Object>>,
+    /// De unde citește `citește`.
+    pub input: LineReader,
+    /// Unde scrie `scrie`.
+    pub output: Box<dyn Write>,
+    /// Restul liniei curente, încă neconsumat de `citește`.
+    pending: String,
+    /// La sfârșitul datelor de intrare, `citește` dă `nul` în loc de eroare.
+    pub lenient: bool,
+    /// Interzice `citește din` și `scrie în`, de exemplu la evaluare.
+    pub sandbox: bool,
+    pub arithmetic: Arithmetic,
+    /// Cum scrie `scrie` numerele și valorile logice.
+    pub format: Format,
+    /// Numele cuvintelor cheie care se pot redenumi.
+    pub dialect: Dialect,
+    /// Un `întrerupe` sau `continuă` care nu a ajuns încă la bucla lui.
+    control: Option<Control>,
+    /// Fișierele deschise de `citește din`, fiecare cu restul liniei curente.
+    inputs: HashMap<String, (LineReader, String)>,
+    /// Fișierele deschise de `scrie în`.
+    outputs: HashMap<String, Box<dyn Write>>,
+    pub limits: Limits,
+    pub usage: Usage,
+    /// Linia ultimei instrucțiuni începute.
+    pub line: usize,
+    /// Când este setat (de exemplu de un handler de SIGINT), execuția se
+    /// oprește la următorul pas cu eroarea `Interrupted`.
+    pub interrupt: Option<Arc<AtomicBool>>,
+    pub hook: Option<Box<dyn Hook>>,
+    /// Câte blocuri de instrucțiuni sunt în execuție (1 pentru programul
+    /// principal).
+    pub nesting: usize,
+    /// Tipurile declarate prin `citește x (număr natural)` și altele;
+    /// atribuirile următoare trebuie să le respecte.
+    pub declared: HashMap<String, Type>,
+    started: Instant,
 }
 
 impl Environment {
     pub fn new() -> Environment {
         Environment {
             syms: HashMap::new(),
+            input: Box::new(|buf| stdin().read_line(buf)),
+            output: Box::new(stdout()),
+            pending: String::new(),
+            lenient: false,
+            sandbox: false,
+            arithmetic: Arithmetic::default(),
+            format: Format::default(),
+            dialect: Dialect::default(),
+            control: None,
+            inputs: HashMap::new(),
+            outputs: HashMap::new(),
+            limits: Limits::default(),
+            usage: Usage::default(),
+            line: 0,
+            interrupt: None,
+            hook: None,
+            nesting: 0,
+            declared: HashMap::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Resetează contoarele înaintea unei noi execuții; variabilele rămân.
+    pub fn reset_usage(&mut self) {
+        self.usage = Usage::default();
+        self.nesting = 0;
+        self.control = None;
+        self.started = Instant::now();
+    }
+
+    fn exceeded(&self, limit: Limit) -> anyhow::Error {
+        anyhow::Error::new(LimitExceeded {
+            limit,
+            line: self.line,
+            usage: self.usage,
+            limits: self.limits,
+        })
+    }
+
+    /// Numără o instrucțiune executată și verifică întreruperile și limitele
+    /// de pași și timp.
+    pub fn step(&mut self) -> Result<()> {
+        if let Some(flag) = &self.interrupt && flag.swap(false, AtomicOrdering::SeqCst) {
+            return Err(anyhow::Error::new(Interrupted { line: self.line }));
+        }
+        self.usage.steps += 1;
+        self.usage.elapsed = self.started.elapsed();
+        if let Some(max) = self.limits.max_steps && self.usage.steps > max {
+            return Err(self.exceeded(Limit::Steps));
+        }
+        if let Some(timeout) = self.limits.timeout && self.usage.elapsed > timeout {
+            return Err(self.exceeded(Limit::Time));
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self, node: &ASTNode) -> Result<()> {
+        if node.line > 0 {
+            self.line = node.line;
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Intră într-un bloc de instrucțiuni și verifică limita de adâncime.
+    fn enter_block(&mut self) -> Result<()> {
+        self.nesting += 1;
+        if self.nesting > self.usage.max_depth {
+            self.usage.max_depth = self.nesting;
+        }
+        if let Some(max) = self.limits.max_depth && self.nesting > max {
+            self.nesting -= 1;
+            return Err(self.exceeded(Limit::Depth));
+        }
+        Ok(())
+    }
+
+    fn check_sandbox(&self, path: &str) -> Result<()> {
+        if self.sandbox {
+            return Err(anyhow!("Accesul la fișiere este dezactivat (`--izolat`); nu pot deschide `{}`.", path));
+        }
+        Ok(())
+    }
+
+    /// Schimbă sursa lui `citește` cu fișierul `path`, deschis la prima
+    /// folosire; un al doilea apel pune sursa inițială înapoi.
+    pub fn swap_input(&mut self, path: &str) -> Result<()> {
+        if !self.inputs.contains_key(path) {
+            self.check_sandbox(path)?;
+            let mut reader = BufReader::new(File::open(path).map_err(|e| anyhow!("Nu pot deschide fișierul `{}`: {}.", path, e))?);
+            self.inputs.insert(path.to_string(), (Box::new(move |buf| reader.read_line(buf)), String::new()));
+        }
+        let (input, pending) = self.inputs.get_mut(path).unwrap();
+        mem::swap(&mut self.input, input);
+        mem::swap(&mut self.pending, pending);
+        Ok(())
+    }
+
+    /// Schimbă destinația lui `scrie` cu fișierul `path`, creat (sau golit)
+    /// la prima folosire; un al doilea apel pune destinația inițială înapoi.
+    pub fn swap_output(&mut self, path: &str) -> Result<()> {
+        if !self.outputs.contains_key(path) {
+            self.check_sandbox(path)?;
+            let file = File::create(path).map_err(|e| anyhow!("Nu pot crea fișierul `{}`: {}.", path, e))?;
+            self.outputs.insert(path.to_string(), Box::new(file));
+        }
+        mem::swap(&mut self.output, self.outputs.get_mut(path).unwrap());
+        Ok(())
+    }
+
+    /// Sare peste spații și linii goale până la următorul caracter din date.
+    fn skip_blank(&mut self) -> Result<()> {
+        loop {
+            let rest = self.pending.trim_start();
+            if !rest.is_empty() {
+                self.pending = rest.to_string();
+                return Ok(());
+            }
+            self.pending.clear();
+            if (self.input)(&mut self.pending)? == 0 {
+                return Err(anyhow!("Nu mai sunt date de intrare."));
+            }
+        }
+    }
+
+    /// Citește următorul cuvânt separat prin spații, trecând peste linii.
+    pub fn read_token(&mut self) -> Result<String> {
+        self.skip_blank()?;
+        let end = self.pending.find(char::is_whitespace).unwrap_or(self.pending.len());
+        let token = self.pending[..end].to_string();
+        self.pending.drain(..end);
+        Ok(token)
+    }
+
+    /// Citește următoarea valoare pentru variabila `name`: de tipul dat sau,
+    /// fără adnotare, număr întreg, real sau șir de caractere, după cum arată.
+    /// La sfârșitul datelor întoarce `nul` în modul tolerant și o eroare
+    /// altfel.
+    pub fn read_value(&mut self, name: &str, t: Option<VarType>) -> Result<Object> {
+        if self.skip_blank().is_err() {
+            if self.lenient {
+                return Ok(Object::Null);
+            }
+            return Err(anyhow!("Nu mai sunt date de intrare pentru `{}`.", name));
+        }
+        if t == Some(VarType::Char) {
+            let c = self.pending.chars().next().unwrap();
+            self.pending.drain(..c.len_utf8());
+            return Ok(Object::String(c.to_string()));
+        }
+        let v = self.read_token()?;
+        let invalid = |what: &str| anyhow!("Valoarea `{}` citită în `{}` nu este {}.", v, name, what);
+        Ok(match t {
+            Some(VarType::Natural) => self.parse_integer(&v).filter(|x| ops::compare(x, &Object::Int(0)).is_some_and(|o| o.is_ge())).ok_or_else(|| invalid("un număr natural"))?,
+            Some(VarType::Integer) => self.parse_integer(&v).ok_or_else(|| invalid("un număr întreg"))?,
+            Some(VarType::Real) => Object::Float(v.parse::<f64>().ok().filter(|x| x.is_finite()).ok_or_else(|| invalid("un număr"))?),
+            Some(_) => Object::String(v),
+            None => {
+                let numeric = v.chars().all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E'));
+                match (self.parse_integer(&v), v.parse::<f64>()) {
+                    (Some(x), _) => x,
+                    (_, Ok(x)) if numeric && x.is_finite() => Object::Float(x),
+                    _ => Object::String(v),
+                }
+            }
+        })
+    }
+
+    /// O valoare așa cum o scrie `scrie`, cu formatul global sau cu
+    /// `decimals` zecimale, ca în `scrie x : 2`.
+    pub fn show(&self, value: &Object, decimals: Option<usize>) -> String {
+        let cpp = matches!(self.arithmetic.integers, Integers::Cpp(_));
+        match decimals {
+            Some(decimals) => Format { decimals: Some(decimals), ..self.format.clone() }.show(value, cpp),
+            None => self.format.show(value, cpp),
+        }
+    }
+
+    /// Un număr întreg scris în `v`; cu `--bigint`, oricât de mare, iar cu
+    /// `--cpp`, redus la lățimea aleasă.
+    pub fn parse_integer(&self, v: &str) -> Option<Object> {
+        match v.parse::<i64>() {
+            Ok(x) => Some(Object::Int(self.arithmetic.narrow(x))),
+            Err(_) if self.arithmetic.integers == Integers::Big => v.parse::<BigInt>().ok().map(ops::int),
+            Err(_) => None,
+        }
+    }
+
+    /// Un real fără zecimale ca număr întreg, dacă încape în `i64` sau, cu
+    /// `--bigint`, oricât de mare.
+    fn whole(&self, x: f64) -> Option<Object> {
+        if x.fract() != 0.0 {
+            None
+        } else if x >= i64::MIN as f64 && x < i64::MAX as f64 {
+            Some(Object::Int(x as i64))
+        } else if self.arithmetic.integers == Integers::Big {
+            BigInt::from_f64(x).map(Object::BigInt)
+        } else {
+            None
+        }
+    }
+
+    /// Verifică o valoare față de tipul declarat al variabilei și o aduce la
+    /// forma lui: un real fără zecimale devine întreg, un întreg devine real.
+    pub fn check_declared(&self, name: &str, value: Object) -> Result<Object> {
+        let declared = match self.declared.get(name) {
+            Some(t) => *t,
+            None => return Ok(value),
+        };
+        let converted = match (declared, &value) {
+            (Type::Natural, Object::Int(x)) if *x >= 0 => Some(value.clone()),
+            (Type::Natural, Object::Float(x)) if *x >= 0.0 => self.whole(*x),
+            (Type::Natural, Object::BigInt(x)) if x.sign() != Sign::Minus => Some(value.clone()),
+            (Type::Integer, Object::Int(_) | Object::BigInt(_)) => Some(value.clone()),
+            (Type::Integer, Object::Float(x)) => self.whole(*x),
+            (Type::Real, Object::Int(x)) => Some(Object::Float(*x as f64)),
+            (Type::Real, Object::BigInt(x)) => x.to_f64().filter(|x| x.is_finite()).map(Object::Float),
+            (Type::Real, Object::Float(_) | Object::Rational(_)) => Some(value.clone()),
+            (Type::String, Object::String(_)) => Some(value.clone()),
+            _ => None,
+        };
+        converted.ok_or_else(|| {
+            let shown = match &value {
+                Object::String(x) => format!("{:?}", x),
+                x => x.to_string(),
+            };
+            anyhow!("Variabila `{}` este declarată {} și nu poate primi valoarea {} (linia {}).", name, declared, shown, self.line)
+        })
+    }
+
+    /// Elementul `name[i][j]...`, sau `None` dacă nu a fost atribuit. Fiecare
+    /// indice în afară de ultimul trebuie să ducă la un tablou.
+    fn element(&self, name: &str, indices: &[i64]) -> Result<Option<&Object>> {
+        let mut value = match self.syms.get(name) {
+            Some(x) => x.as_ref(),
+            None => return Ok(None),
+        };
+        for i in indices {
+            value = match value {
+                Object::Array(items) => match items.get(i) {
+                    Some(x) => x,
+                    None => return Ok(None),
+                },
+                Object::Null => return Ok(None),
+                x => return Err(anyhow!("`{}` nu este un tablou, ci {}.", name, ops::kind(x))),
+            };
+        }
+        Ok(Some(value))
+    }
+
+    /// Scrie `value` în variabila `name` sau, cu indici, într-un element al
+    /// ei, creând tablourile care lipsesc.
+    fn store(&mut self, name: &str, indices: &[i64], value: Object) -> Result<()> {
+        if indices.is_empty() {
+            self.find_variable(name, Some(Box::new(value)));
+            return Ok(());
+        }
+        let mut slot = self.syms.entry(name.to_string()).or_insert_with(|| Box::new(Object::Null)).as_mut();
+        for i in indices {
+            if *slot == Object::Null {
+                *slot = Object::Array(BTreeMap::new());
+            }
+            slot = match slot {
+                Object::Array(items) => items.entry(*i).or_insert(Object::Null),
+                x => return Err(anyhow!("`{}` nu este un tablou, ci {}.", name, ops::kind(x))),
+            };
         }
+        *slot = value;
+        Ok(())
     }
 
     pub fn find_variable(&mut self, key: &str, value: Option<Box<Object>>) -> Option<Box<Object>> {
-        if matches!(key, "scrie" | "citește") {
+        if builtins::is_builtin(key) {
             return None;
         }
         if let Some(obj) = self.syms.get(key) {
@@ -85,153 +500,6 @@ impl Environment {
     }
 }
 
-macro_rules! perform_operation {
-    ($left:expr, $right:expr, $env:expr, $operator:tt) => {
-        {
-            let left = eval($left.clone(), Some($env))?;
-            let right = eval($right.clone(), Some($env))?;
-            let ret: Object = match left {
-                Object::Float(leftv) => match right {
-                    Object::Int(right) => Object::Float(leftv $operator right as f64),
-                    Object::Float(right) => Object::Float(leftv $operator right),
-                    Object::Bool(right) => Object::Float(leftv $operator match right {
-                        true => 1.0,
-                        false => 0.0,
-                    }),
-                    Object::String(x) => Object::String(leftv.to_string() + x.as_str()),
-                    Object::Null => left,
-                    _ => unimplemented!(),
-                },
-                Object::Int(leftv) => match right {
-                    Object::Int(right) => Object::Int(leftv $operator right),
-                    Object::Float(right) => Object::Float(leftv as f64 $operator right),
-                    Object::Bool(right) => Object::Int(leftv $operator match right {
-                        true => 1,
-                        false => 0,
-                    }),
-                    Object::String(x) => Object::String(leftv.to_string() + x.as_str()),
-                    Object::Null => left,
-                    _ => unimplemented!(),
-                },
-                Object::Bool(leftv) => match right {
-                    Object::Int(right) => Object::Int(match leftv {
-                        true => 1,
-                        false => 0,
-                    } $operator right),
-                    Object::Float(right) => Object::Float(match leftv {
-                        true => 1.0,
-                        false => 0.0,
-                    } $operator right),
-                    Object::Bool(right) => Object::Int(match leftv {
-                        true => 1,
-                        false => 0,
-                    } $operator match right {
-                        true => 1,
-                        false => 0,
-                    }),
-                    Object::String(x) => Object::String(match leftv {
-                        true => String::from("adevărat"),
-                        false => String::from("fals"),
-                    } + x.as_str()),
-                    Object::Null => left,
-                    _ => unimplemented!(),
-                },
-                Object::String(leftv) => match right {
-                    Object::Int(right) => Object::String(leftv + &right.to_string()),
-                    Object::Float(right) => Object::String(leftv + &right.to_string()),
-                    Object::Bool(right) => Object::String(leftv + match right {
-                        true => "adevărat",
-                        false => "fals",
-                    }),
-                    Object::String(right) => Object::String(leftv + &right.to_string()),
-                    Object::Null => Object::String(leftv + "nul"),
-                    _ => unimplemented!(),
-                },
-                Object::Null => left,
-                _ => unimplemented!(),
-            };
-            Ok(ret)
-        }
-    };
-}
-
-macro_rules! perform_logical_operation {
-    ($left:expr, $right:expr, $env:expr, $operator:tt) => {
-        {
-            let left = eval($left.clone(), Some($env))?;
-            let right = eval($right.clone(), Some($env))?;
-            Ok(Object::Bool(left $operator right))
-        }
-    };
-}
-
-macro_rules! perform_operation_term {
-    ($left:expr, $right:expr, $env:expr, $operator:tt) => {
-        {
-            let left = eval($left.clone(), Some($env))?;
-            let right = eval($right.clone(), Some($env))?;
-            let ret: Object = match left {
-                Object::Float(leftv) => match right {
-                    Object::Int(right) => Object::Float(leftv $operator right as f64),
-                    Object::Float(right) => Object::Float(leftv $operator right),
-                    Object::Bool(right) => Object::Float(leftv $operator match right {
-                        true => 1.0,
-                        false => 0.0,
-                    }),
-                    Object::String(_) => Object::Null,
-                    Object::Null => left,
-                    _ => unimplemented!(),
-                },
-                Object::Int(leftv) => match right {
-                    Object::Int(right) => Object::Float(leftv as f64 $operator right as f64),
-                    Object::Float(right) => Object::Float(leftv as f64 $operator right),
-                    Object::Bool(right) => Object::Int(leftv $operator match right {
-                        true => 1,
-                        false => 0,
-                    }),
-                    Object::String(_) => Object::Null,
-                    Object::Null => left,
-                    _ => unimplemented!(),
-                },
-                Object::Bool(leftv) => match right {
-                    Object::Int(right) => Object::Int(match leftv {
-                        true => 1,
-                        false => 0,
-                    } $operator right),
-                    Object::Float(right) => Object::Float(match leftv {
-                        true => 1.0,
-                        false => 0.0,
-                    } $operator right),
-                    Object::Bool(right) => Object::Int(match leftv {
-                        true => 1,
-                        false => 0,
-                    } $operator match right {
-                        true => 1,
-                        false => 0,
-                    }),
-                    Object::String(_) => Object::Null,
-                    Object::Null => left,
-                    _ => unimplemented!(),
-                },
-                Object::String(x) => Object::String(x.repeat(match right {
-                    Object::Int(j) => j as usize,
-                    Object::Float(j) => j as usize,
-                    Object::Bool(j) => match j {
-                        true => 1,
-                        false => 0,
-                    },
-                    Object::String(_) => 0,
-                    Object::Null => 0,
-                    _ => unimplemented!(),
-                })),
-                Object::Null => left,
-                _ => unimplemented!(),
-            };
-            Ok(ret)
-        }
-    };
-}
-
 pub fn truthy(obj: Object) -> bool {
     match obj {
         Object::Null => false,
@@ -243,14 +511,123 @@ pub fn truthy(obj: Object) -> bool {
     }
 }
 
-fn reverse(s: String) -> String {
-    s.chars().rev().collect()
+fn notify(envb: &Rc<RefCell<Environment>>, event: Event) -> Result<()> {
+    let hook = envb.borrow_mut().hook.take();
+    match hook {
+        Some(mut hook) => {
+            let res = hook.event(event, envb);
+            envb.borrow_mut().hook = Some(hook);
+            res
+        }
+        None => Ok(()),
+    }
+}
+
+/// Variabila și indicii calculați ai lui `v` sau `v[i][j]`.
+fn place<'a>(target: &'a ASTNode, envb: &Rc<RefCell<Environment>>) -> Result<(&'a str, Vec<i64>)> {
+    match (&target.value, target.children.as_slice()) {
+        (ASTNodeValue::Identifier(name), _) => Ok((name, Vec::new())),
+        (ASTNodeValue::Index, [array, index]) => {
+            let (name, mut indices) = place(array, envb)?;
+            indices.push(match eval(index.clone(), Some(envb.clone()))? {
+                Object::Int(i) => i,
+                x => return Err(anyhow!("Indicele unui tablou trebuie să fie un număr întreg, nu {}.", ops::kind(&x))),
+            });
+            Ok((name, indices))
+        }
+        _ => Err(anyhow!("Se poate atribui doar unei variabile.")),
+    }
+}
+
+/// Atribuie valorile variabilelor sau elementelor de tablou, în ordine.
+/// Toate valorile și toți indicii se calculează și se verifică înainte ca
+/// vreo variabilă să se schimbe, deci `a, b ← b, a` și `v[i] ↔ v[j]`
+/// interschimbă. Întoarce ultima valoare atribuită.
+fn assign(targets: &[Box<ASTNode>], values: &[Box<ASTNode>], envb: &Rc<RefCell<Environment>>) -> Result<Object> {
+    let mut results = Vec::new();
+    for (target, value) in targets.iter().zip(values) {
+        let (name, indices) = place(target, envb)?;
+        let value = eval(value.clone(), Some(envb.clone()))?;
+        results.push((name, indices, value));
+    }
+    let mut checked = Vec::new();
+    for (name, indices, value) in results {
+        let env = envb.borrow();
+        let value = if indices.is_empty() {
+            env.check_declared(name, value)?
+        } else {
+            env.element(name, &indices)?;
+            value
+        };
+        checked.push((name, indices, value));
+    }
+    let mut res = Object::Null;
+    for (name, indices, value) in checked {
+        envb.borrow_mut().store(name, &indices, value.clone())?;
+        res = value;
+    }
+    Ok(res)
+}
+
+/// Separă fișierul din `citește din` / `scrie în` de restul argumentelor.
+fn split_file(args: &[Box<ASTNode>]) -> (Option<&str>, &[Box<ASTNode>]) {
+    match args.first().map(|a| &a.value) {
+        Some(ASTNodeValue::File(path)) => (Some(path), &args[1..]),
+        _ => (None, args),
+    }
+}
+
+/// Apelează o funcție predefinită: verifică numărul argumentelor, calculează
+/// valorile și, pentru `citește din` / `scrie în`, schimbă fluxul cu fișierul
+/// pe durata apelului.
+fn call_builtin(builtin: &Builtin, root: &ASTNode, envb: &Rc<RefCell<Environment>>) -> Result<Object> {
+    let (file, nodes) = split_file(&root.children);
+    builtin.check_arity(nodes.len())?;
+    let mut args = Vec::new();
+    for (i, node) in nodes.iter().enumerate() {
+        args.push(match (builtin.kind(i), &node.value) {
+            (ArgKind::Value, _) => Arg::Value(eval(node.clone(), Some(envb.clone()))?),
+            (ArgKind::Reference, ASTNodeValue::Identifier(x)) => Arg::Reference(x.clone(), None),
+            (ArgKind::Reference, ASTNodeValue::Cast(x, t)) => Arg::Reference(x.clone(), Some(*t)),
+            (ArgKind::Reference, _) => return Err(anyhow!("Argumentul {} al funcției `{}` trebuie să fie o variabilă.", i + 1, builtin.name)),
+        });
+    }
+    let swap = |env: &mut Environment, path: &str| match builtin.file {
+        Some((_, Stream::Input)) => env.swap_input(path),
+        Some((_, Stream::Output)) => env.swap_output(path),
+        None => Err(anyhow!("Funcția `{}` nu lucrează cu fișiere.", builtin.name)),
+    };
+    let res = {
+        let mut env = envb.borrow_mut();
+        match file {
+            Some(path) => {
+                swap(&mut env, path)?;
+                let res = (builtin.call)(&mut env, args);
+                swap(&mut env, path)?;
+                res
+            }
+            None => (builtin.call)(&mut env, args),
+        }
+    }?;
+    if builtin.assigns() {
+        notify(envb, Event::Read(root))?;
+    }
+    Ok(res)
 }
 
 pub fn eval(root: Box<ASTNode>, parent: Option<Rc<RefCell<Environment>>>) -> Result<Object> {
     let envb = parent.unwrap();
+    envb.borrow_mut().enter(&root)?;
+    eval_node(root, envb)
+}
+
+fn eval_node(root: Box<ASTNode>, envb: Rc<RefCell<Environment>>) -> Result<Object> {
     match root.value {
-        ASTNodeValue::Int(x) => Ok(Object::Int(x)),
+        ASTNodeValue::Int(x) => Ok(Object::Int(envb.borrow().arithmetic.narrow(x))),
+        ASTNodeValue::BigInt(x) => match envb.borrow().arithmetic.integers {
+            Integers::Big => Ok(Object::BigInt(x)),
+            _ => Err(anyhow!("Numărul {} este prea mare pentru un număr întreg; rulează cu `--bigint` pentru numere mari.", x)),
+        },
         ASTNodeValue::Float(x) => Ok(Object::Float(x)),
         ASTNodeValue::Bool(x) => Ok(Object::Bool(x)),
         ASTNodeValue::String(x) => Ok(Object::String(x)),
@@ -261,213 +638,183 @@ pub fn eval(root: Box<ASTNode>, parent: Option<Rc<RefCell<Environment>>>) -> Res
                 None => Ok(Object::Null),
             }
         }
+        ASTNodeValue::Index => {
+            let (name, indices) = place(&root, &envb)?;
+            Ok(envb.borrow().element(name, &indices)?.cloned().unwrap_or(Object::Null))
+        }
         ASTNodeValue::If => {
-            let cond = eval(root.children[0].clone(), Some(envb.clone()))?;
-            if truthy(cond) {
-                eval(root.children[1].clone(), Some(envb.clone()))
-            } else {
-                if root.children.len() == 3 {
-                    eval(root.children[2].clone(), Some(envb.clone()))
-                } else {
-                    Ok(Object::Null)
+            for branch in root.children.chunks(2) {
+                match branch {
+                    [cond, body] if truthy(eval(cond.clone(), Some(envb.clone()))?) => return eval(body.clone(), Some(envb.clone())),
+                    [other] => return eval(other.clone(), Some(envb.clone())),
+                    _ => (),
                 }
             }
+            Ok(Object::Null)
         }
         ASTNodeValue::While => {
             let mut ret = Ok(Object::Null);
-            while truthy(eval(root.children[0].clone(), Some(envb.clone()))?) {
-                ret = Ok(eval(root.children[1].clone(), Some(envb.clone()))?)
+            loop {
+                envb.borrow_mut().step()?;
+                let cond = truthy(eval(root.children[0].clone(), Some(envb.clone()))?);
+                notify(&envb, Event::LoopTest(&root, cond))?;
+                if !cond {
+                    break;
+                }
+                ret = Ok(eval(root.children[1].clone(), Some(envb.clone()))?);
+                if envb.borrow_mut().control.take() == Some(Control::Break) {
+                    break;
+                }
             }
             ret
         }
         ASTNodeValue::Until => {
             let mut ret = Ok(Object::Null);
-            while !truthy(eval(root.children[0].clone(), Some(envb.clone()))?) {
-                ret = Ok(eval(root.children[1].clone(), Some(envb.clone()))?)
+            loop {
+                envb.borrow_mut().step()?;
+                let cond = truthy(eval(root.children[0].clone(), Some(envb.clone()))?);
+                notify(&envb, Event::LoopTest(&root, !cond))?;
+                if cond {
+                    break;
+                }
+                ret = Ok(eval(root.children[1].clone(), Some(envb.clone()))?);
+                if envb.borrow_mut().control.take() == Some(Control::Break) {
+                    break;
+                }
             }
             ret
         }
         ASTNodeValue::Execute => {
-            let mut ret = Ok(eval(root.children[0].clone(), Some(envb.clone()))?);
-            let mut loopy = root.children[1].clone();
-            loopy.children.push(root.children[0].clone());
-            let val_loopy = eval(loopy, Some(envb.clone()));
-            if let Ok(v) = val_loopy {
-                ret = Ok(v);
-            } else if let Err(e) = val_loopy {
-                ret = Err(e);
+            let ret = eval(root.children[0].clone(), Some(envb.clone()))?;
+            if envb.borrow_mut().control.take() == Some(Control::Break) {
+                return Ok(ret);
             }
-            ret
+            let Some(header) = root.children.get(1) else {
+                return Ok(ret);
+            };
+            let mut loopy = header.clone();
+            loopy.children.push(root.children[0].clone());
+            eval(loopy, Some(envb.clone()))
         }
         ASTNodeValue::For => {
-            let varname = &root.children[0].children[0];
-            let varname = match &varname.value {
-                ASTNodeValue::Identifier(x) => x,
-                _ => unreachable!(),
-            };
-            let start = match eval(root.children[0].clone(), Some(envb.clone()))? {
-                Object::Int(x) => x,
-                _ => unreachable!("Dacă acceptă doar Int"),
-            };
-            let end = match eval(root.children[1].clone(), Some(envb.clone()))? {
-                Object::Int(x) => x,
-                _ => unreachable!("Dacă acceptă doar Int"),
+            // `pentru i ← a, b, p` este `i ← a; cât timp i ≤ b (i ≥ b dacă
+            // p < 0): corp; i ← i + p`. `b` și `p` se evaluează o singură dată.
+            let set = &root.children[0];
+            let ASTNodeValue::Identifier(counter) = &set.children[0].value else {
+                return Err(anyhow!("`pentru` are nevoie de o variabilă contor."));
             };
+            let mode = envb.borrow().arithmetic;
+            eval(set.clone(), Some(envb.clone()))?;
+            let end = eval(root.children[1].clone(), Some(envb.clone()))?;
             let step = if root.children.len() == 4 {
-                match eval(root.children[2].clone(), Some(envb.clone()))? {
-                    Object::Int(x) => x,
-                    _ => unreachable!("Dacă acceptă doar Int"),
-                }
+                eval(root.children[2].clone(), Some(envb.clone()))?
             } else {
-                1
+                Object::Int(1)
+            };
+            if let Some(x) = [&end, &step].into_iter().find(|x| !ops::is_number(x)) {
+                return Err(anyhow!("`pentru` parcurge doar numere, nu {} ({}).", ops::kind(x), x));
+            }
+            // Bucla se oprește când contorul trece de capăt în direcția pasului.
+            let past = match ops::compare(&step, &Object::Int(0)) {
+                Some(std::cmp::Ordering::Equal) => return Err(anyhow!("Pasul lui `pentru` nu poate fi 0.")),
+                None => return Err(anyhow!("Pasul lui `pentru` nu poate fi {}.", step)),
+                Some(direction) => direction,
             };
+            let body = root.children.last().unwrap().clone();
             let mut ret = Ok(Object::Null);
-
-            for n in (start..=end).step_by(step.try_into().unwrap()) {
-                envb.borrow_mut().find_variable(varname, Some(Box::new(Object::Int(n))));
-                if root.children.len() == 4 {
-                    ret = eval(root.children[3].clone(), Some(envb.clone()));
-                } else {
-                    ret = eval(root.children[2].clone(), Some(envb.clone()));
+            loop {
+                envb.borrow_mut().step()?;
+                let current = envb.borrow_mut().find_variable(counter, None).map_or(Object::Null, |x| *x);
+                let cond = match ops::compare(&current, &end) {
+                    Some(o) if ops::is_number(&current) => o != past,
+                    _ => return Err(anyhow!("Contorul `{}` al lui `pentru` trebuie să fie un număr, nu {} ({}).", counter, ops::kind(&current), current)),
+                };
+                notify(&envb, Event::LoopTest(&root, cond))?;
+                if !cond {
+                    break;
+                }
+                ret = Ok(eval(body.clone(), Some(envb.clone()))?);
+                if envb.borrow_mut().control.take() == Some(Control::Break) {
+                    break;
                 }
-                envb.borrow_mut().find_variable(varname, Some(Box::new(Object::Int(n))));
+                let current = envb.borrow_mut().find_variable(counter, None).map_or(Object::Null, |x| *x);
+                let next = ops::binary(mode, BinOp::Add, &current, &step)?;
+                // Valoarea de după ultimul pas poate ieși din tipul declarat
+                // (`-1` pentru un contor natural care coboară până la 0).
+                let next = if ops::compare(&next, &end) == Some(past) {
+                    next
+                } else {
+                    envb.borrow().check_declared(counter, next)?
+                };
+                envb.borrow_mut().find_variable(counter, Some(Box::new(next)));
             }
             ret
         }
         ASTNodeValue::Set => {
-            if let ASTNodeValue::Identifier(ident) = &root.children[0].value {
-                let value = eval(root.children[1].clone(), Some(envb.clone()))?;
-                let res = envb.borrow_mut().find_variable(ident, Some(Box::new(value)));
-                if let Some(val) = res {
-                    Ok(*val.clone())
-                } else {
-                    Ok(Object::Null)
-                }
-            } else {
-                unreachable!()
-            }
+            let (targets, values) = root.assignment();
+            let res = assign(targets, values, &envb)?;
+            notify(&envb, Event::Set(&root))?;
+            Ok(res)
+        }
+        ASTNodeValue::Swap => {
+            let (a, b) = (&root.children[0], &root.children[1]);
+            let res = assign(&[a.clone(), b.clone()], &[b.clone(), a.clone()], &envb)?;
+            notify(&envb, Event::Set(&root))?;
+            Ok(res)
         }
         ASTNodeValue::Program => {
-            let mut ret: Object = Object::Null;
-            for child in root.children {
-                ret = eval(child, Some(envb.clone()))?;
-            }
-            Ok(ret)
-        }
-        ASTNodeValue::Equal => perform_logical_operation!(root.children[0], root.children[1], envb.clone(), ==),
-        ASTNodeValue::NotEqual => perform_logical_operation!(root.children[0], root.children[1], envb.clone(), !=),
-        ASTNodeValue::LessThan => perform_logical_operation!(root.children[0], root.children[1], envb.clone(), <),
-        ASTNodeValue::LessThanEqual => perform_logical_operation!(root.children[0], root.children[1], envb.clone(), <=),
-        ASTNodeValue::GreaterThan => perform_logical_operation!(root.children[0], root.children[1], envb.clone(), >),
-        ASTNodeValue::GreaterThanEqual => perform_logical_operation!(root.children[0], root.children[1], envb.clone(), >=),
-        ASTNodeValue::Add => perform_operation!(root.children[0], root.children[1], envb.clone(), +),
-        ASTNodeValue::Subtract => {
-            if root.children.len() == 1 {
-                match eval(root.children[0].clone(), Some(envb.clone()))? {
-                    Object::Int(x) => Ok(Object::Int(-x)),
-                    Object::Float(x) => Ok(Object::Float(-x)),
-                    Object::Bool(x) => Ok(Object::Bool(!x)),
-                    Object::String(x) => Ok(Object::String(reverse(x))),
-                    Object::Null => Ok(Object::Null),
-                    _ => unimplemented!(),
+            envb.borrow_mut().enter_block()?;
+            let ret = (|| -> Result<Object> {
+                let mut ret: Object = Object::Null;
+                for child in root.children {
+                    notify(&envb, Event::Statement(&child))?;
+                    ret = eval(child, Some(envb.clone()))?;
+                    if envb.borrow().control.is_some() {
+                        break;
+                    }
                 }
-            } else {
-                perform_operation!(root.children[0], root.children[1], envb.clone(), -)
-            }
+                Ok(ret)
+            })();
+            envb.borrow_mut().nesting -= 1;
+            ret
         }
-        ASTNodeValue::Multiply => perform_operation_term!(root.children[0], root.children[1], envb.clone(), *),
-        ASTNodeValue::Divide => {
-            if let ASTNodeValue::String(s) = &root.children[0].value {
-                Ok(Object::String(s.chars().take(s.chars().count()/match eval(root.children[1].clone(), Some(envb.clone()))? {
-                    Object::Int(x) => x as usize,
-                    Object::Float(x) => x as usize,
-                    _ => return Err(anyhow!("Nu poți împărți un șir de caractere așa.")),
-                }).collect()))
-            } else if let ASTNodeValue::String(_) = root.children[1].value {
-                Err(anyhow!("Nu poți împărți la un șir de caracteretring."))
-            } else if root.children[1].value == ASTNodeValue::Null {
-                Err(anyhow!("Împărțirea la zero este ilegală."))
-            } else {
-                perform_operation_term!(root.children[0], root.children[1], envb.clone(), /)
-            }
+        ASTNodeValue::Subtract if root.children.len() == 1 => {
+            let mode = envb.borrow().arithmetic;
+            ops::negate(mode, &eval(root.children[0].clone(), Some(envb.clone()))?)
         }
-        ASTNodeValue::Mod => {
-            if root.children[1].value == ASTNodeValue::Null {
-                Err(anyhow!("Modul la zero este ilegal."))
-            } else {
-                perform_operation!(root.children[0], root.children[1], envb.clone(), %)
-            }
+        ASTNodeValue::Add | ASTNodeValue::Subtract | ASTNodeValue::Multiply | ASTNodeValue::Divide |
+        ASTNodeValue::Mod | ASTNodeValue::Power | ASTNodeValue::Equal | ASTNodeValue::NotEqual |
+        ASTNodeValue::LessThan | ASTNodeValue::GreaterThan | ASTNodeValue::LessThanEqual | ASTNodeValue::GreaterThanEqual => {
+            let op = BinOp::from_node(&root.value).unwrap();
+            let left = eval(root.children[0].clone(), Some(envb.clone()))?;
+            let right = eval(root.children[1].clone(), Some(envb.clone()))?;
+            let mode = envb.borrow().arithmetic;
+            ops::binary(mode, op, &left, &right)
         }
         ASTNodeValue::Floor => {
-            let val = eval(root.children[0].clone(), Some(envb.clone()))?;
-            match val {
-                Object::Int(x) => Ok(Object::Int(x)),
-                Object::Float(x) => Ok(Object::Int(x.floor() as i64)),
-                Object::Bool(_) => Err(anyhow!("Nu poti rotunji în jos un bool.")),
-                Object::String(_) => Err(anyhow!("Nu poti rotunji în jos un șir de caractere.")),
-                Object::Null => Ok(Object::Null),
-                _ => unimplemented!(),
+            let mode = envb.borrow().arithmetic;
+            let inner = &root.children[0];
+            if inner.value == ASTNodeValue::Divide {
+                let left = eval(inner.children[0].clone(), Some(envb.clone()))?;
+                let right = eval(inner.children[1].clone(), Some(envb.clone()))?;
+                return ops::floor_divide(mode, &left, &right);
             }
+            ops::floor(mode, &eval(inner.clone(), Some(envb.clone()))?)
         }
-        ASTNodeValue::Not => {
-            Ok(match eval(root.children[0].clone(), Some(envb.clone()))? {
-                Object::Null => Object::Bool(true),
-                Object::Bool(x) => Object::Bool(!x),
-                Object::Float(_) => Object::Bool(false),
-                Object::Int(_) => Object::Bool(false),
-                Object::String(_) => Object::Bool(false),
-                _ => unimplemented!(),
-            })
-        }
+        ASTNodeValue::Not => Ok(ops::not(&eval(root.children[0].clone(), Some(envb.clone()))?)),
         ASTNodeValue::And => {
-            Ok(Object::Bool(truthy(eval(root.children[0].clone(), Some(envb.clone()))?) && truthy(eval(root.children[0].clone(), Some(envb.clone()))?)))
+            Ok(Object::Bool(truthy(eval(root.children[0].clone(), Some(envb.clone()))?) && truthy(eval(root.children[1].clone(), Some(envb.clone()))?)))
         }
         ASTNodeValue::Or => {
-            Ok(Object::Bool(truthy(eval(root.children[0].clone(), Some(envb.clone()))?) || truthy(eval(root.children[0].clone(), Some(envb.clone()))?)))
+            Ok(Object::Bool(truthy(eval(root.children[0].clone(), Some(envb.clone()))?) || truthy(eval(root.children[1].clone(), Some(envb.clone()))?)))
         }
-        ASTNodeValue::FunctionCall(name) => {
+        ASTNodeValue::FunctionCall(ref name) => {
+            if let Some(builtin) = builtins::lookup(name) {
+                return call_builtin(builtin, &root, &envb);
+            }
             let lol = &envb.borrow_mut().find_variable(name.as_str(), None);
             match lol {
-                None => {
-                    match name.as_str() {
-                        "scrie" => {
-                            let mut written = 0;
-                            for node in root.children {
-                                let res = eval(node.clone(), Some(envb.clone()))?;
-                                written += res.to_string().chars().count();
-                                print!("{}", res);
-                                std::io::stdout().flush()?;
-                            }
-                            Ok(Object::Int(written as i64))
-                        }
-                        "citește" => {
-                            for node in root.children {
-                                match node.value {
-                                    ASTNodeValue::Identifier(x) => {
-                                        let mut v = String::new();
-                                        stdin().lock().read_line(&mut v)?;
-                                        envb.clone().borrow_mut().find_variable(&x, Some(Box::new(Object::String(v))));
-                                    }
-                                    ASTNodeValue::CastRef(x) => {
-                                        let v: i64 = read!("{}");
-                                        envb.clone().borrow_mut().find_variable(&x, Some(Box::new(Object::Int(v))));
-                                    }
-                                    ASTNodeValue::CastFloatRef(x) => {
-                                        let v: f64 = read!("{}");
-                                        envb.clone().borrow_mut().find_variable(&x, Some(Box::new(Object::Float(v))));
-                                    }
-                                    ASTNodeValue::CastUnsignedRef(x) => {
-                                        let v: u64 = read!("{}");
-                                        envb.clone().borrow_mut().find_variable(&x, Some(Box::new(Object::Int(v as i64))));
-                                    }
-                                    _ => return Err(anyhow!("Citirea formatată nu este implementată."))
-                                }
-                            }
-                            Ok(Object::Null)
-                        }
-                        _ => Err(anyhow!("Nu există funcția `{}`.", name))
-                    }
-                }
+                None => Err(anyhow!("Nu există funcția `{}`.", name)),
                 Some(x) => {
                     if root.children.len() > 0 {
                         unimplemented!("Funcțiile user-defined sunt neimplementate.")
@@ -480,8 +827,303 @@ pub fn eval(root: Box<ASTNode>, parent: Option<Rc<RefCell<Environment>>>) -> Res
         ASTNodeValue::Illegal => {
             todo!("Neimplementat: {}", root.value)
         },
-        ASTNodeValue::CastUnsignedRef(x) => Ok(Object::CastUnsignedRef(x)),
-        ASTNodeValue::CastFloatRef(x) => Ok(Object::CastFloatRef(x)),
-        ASTNodeValue::CastRef(x) => Ok(Object::CastRef(x)),
+        ASTNodeValue::Cast(x, t) => Ok(Object::Cast(x, t)),
+        ASTNodeValue::File(x) => Err(anyhow!("Fișierul `{}` poate apărea doar în `citește din` și `scrie în`.", x)),
+        ASTNodeValue::Break(_) => {
+            envb.borrow_mut().control = Some(Control::Break);
+            Ok(Object::Null)
+        }
+        ASTNodeValue::Continue(_) => {
+            envb.borrow_mut().control = Some(Control::Continue);
+            Ok(Object::Null)
+        }
+        ASTNodeValue::Decimals => {
+            let value = eval(root.children[0].clone(), Some(envb.clone()))?;
+            let decimals = match eval(root.children[1].clone(), Some(envb.clone()))? {
+                Object::Int(d) if d > ops::MAX_DECIMALS as i64 => return Err(anyhow!("Numărul de zecimale din `: {}` depășește maximul de {}.", d, ops::MAX_DECIMALS)),
+                Object::Int(d) if d >= 0 => d as usize,
+                d => return Err(anyhow!("Numărul de zecimale din `: {}` trebuie să fie un număr natural.", d)),
+            };
+            Ok(Object::String(envb.borrow().show(&value, Some(decimals))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, cell::RefCell, time::Duration, sync::{Arc, atomic::AtomicBool}, io::{BufRead, Cursor}};
+    use anyhow::Result;
+
+    use super::{Environment, Object, Limits, Limit, LimitExceeded, Interrupted, eval};
+    use crate::ops::{self, Integers};
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn run(input: &str, limits: Limits) -> Result<Object> {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let root = parser.parse();
+        assert!(parser.errors.is_empty(), "{:?}", parser.errors);
+        let envb = Rc::new(RefCell::new(Environment::new()));
+        envb.borrow_mut().limits = limits;
+        eval(root, Some(envb))
+    }
+
+    fn error_of(res: Result<Object>) -> anyhow::Error {
+        match res {
+            Err(e) => e,
+            Ok(_) => panic!("programul trebuia oprit"),
+        }
+    }
+
+    fn limit_of(res: Result<Object>) -> LimitExceeded {
+        error_of(res).downcast::<LimitExceeded>().expect("se aștepta LimitExceeded")
+    }
+
+    #[test]
+    fn step_limit() {
+        let err = limit_of(run("i <- 0\ncât timp 1 execută\n i <- i + 1\n■\n", Limits { max_steps: Some(100), ..Limits::default() }));
+        assert_eq!(err.limit, Limit::Steps);
+        assert_eq!(err.line, 3);
+        assert_eq!(err.usage.steps, 101);
+    }
+
+    #[test]
+    fn timeout() {
+        let err = limit_of(run("cât timp 1 execută\n■\n", Limits { timeout: Some(Duration::from_millis(20)), ..Limits::default() }));
+        assert_eq!(err.limit, Limit::Time);
+        assert!(err.usage.elapsed >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn depth_limit() {
+        let nested = "a <- 0\ncât timp a < 1 execută\n  dacă a = 0 atunci\n    a <- 1\n  ■\n■\n";
+        let err = limit_of(run(nested, Limits { max_depth: Some(2), ..Limits::default() }));
+        assert_eq!(err.limit, Limit::Depth);
+        assert_eq!(err.line, 3);
+        assert!(run(nested, Limits { max_depth: Some(3), ..Limits::default() }).is_ok());
+        assert!(run("a <- 1 + 2 + 3 + 4 + 5\n", Limits { max_depth: Some(1), ..Limits::default() }).is_ok());
+    }
+
+    #[test]
+    fn interrupt_keeps_variables() {
+        let mut parser = Parser::new(Lexer::new("a <- 7\ncât timp 1 execută\n■\n".to_string()));
+        let root = parser.parse();
+        let envb = Rc::new(RefCell::new(Environment::new()));
+        envb.borrow_mut().interrupt = Some(Arc::new(AtomicBool::new(false)));
+        envb.borrow_mut().limits.max_steps = Some(10);
+        // Steagul e ridicat înainte de rulare, deci prima verificare oprește programul.
+        envb.borrow().interrupt.as_ref().unwrap().store(true, std::sync::atomic::Ordering::SeqCst);
+        let err = error_of(eval(root.clone(), Some(Rc::clone(&envb))));
+        assert!(err.is::<Interrupted>());
+        assert!(!envb.borrow().syms.contains_key("a"));
+
+        assert!(error_of(eval(root, Some(Rc::clone(&envb)))).is::<LimitExceeded>());
+        assert!(*envb.borrow().syms["a"] == Object::Int(7));
+    }
+
+    fn run_with_input(input: &str, data: &str) -> (Rc<RefCell<Environment>>, Result<Object>) {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let root = parser.parse();
+        let envb = Rc::new(RefCell::new(Environment::new()));
+        let mut data = Cursor::new(data.to_string());
+        envb.borrow_mut().input = Box::new(move |buf| data.read_line(buf));
+        let res = eval(root, Some(Rc::clone(&envb)));
+        (envb, res)
+    }
+
+    #[test]
+    fn declared_types() {
+        let (envb, res) = run_with_input("citește n (număr natural), x (număr real)\nn <- n * 2\nx <- 3\n", "5 1.5\n");
+        assert!(res.is_ok());
+        assert!(*envb.borrow().syms["n"] == Object::Int(10));
+        assert!(*envb.borrow().syms["x"] == Object::Float(3.0));
+
+        let (_, res) = run_with_input("citește n (număr natural)\nn <- n - 100\n", "5\n");
+        assert_eq!(error_of(res).to_string(), "Variabila `n` este declarată număr natural și nu poate primi valoarea -95 (linia 2).");
+        let (_, res) = run_with_input("citește n (ref)\n", "abc\n");
+        assert_eq!(error_of(res).to_string(), "Valoarea `abc` citită în `n` nu este un număr întreg.");
+        let (_, res) = run_with_input("citește n (număr natural)\nn <- 'x'\n", "5\n");
+        assert!(error_of(res).to_string().contains("valoarea \"x\""));
+        let (_, res) = run_with_input("citește x (număr întreg)\nx <- 10.0 ^ 30\n", "5\n");
+        assert!(error_of(res).to_string().starts_with("Variabila `x` este declarată număr întreg și nu poate primi valoarea"));
+
+        let mut parser = Parser::new(Lexer::new("citește x (număr întreg)\nx <- 10.0 ^ 30\n".to_string()));
+        let envb = Rc::new(RefCell::new(Environment::new()));
+        envb.borrow_mut().arithmetic.integers = Integers::Big;
+        envb.borrow_mut().input = Box::new(|buf| { buf.push_str("5\n"); Ok(2) });
+        assert!(eval(parser.parse(), Some(Rc::clone(&envb))).is_ok());
+        assert_eq!(envb.borrow().syms["x"].to_string(), "1000000000000000019884624838656");
+    }
+
+    #[test]
+    fn untyped_reads() {
+        let (envb, res) = run_with_input("citește a, b, c\ncitește d\n", "12 -3.5\n  ana\n\n7e2\n");
+        assert!(res.is_ok());
+        let env = envb.borrow();
+        assert!(*env.syms["a"] == Object::Int(12));
+        assert!(*env.syms["b"] == Object::Float(-3.5));
+        assert!(*env.syms["c"] == Object::String("ana".to_string()));
+        assert!(*env.syms["d"] == Object::Float(700.0));
+
+        let (_, res) = run_with_input("citește a, b\n", "1\n");
+        assert_eq!(error_of(res).to_string(), "Nu mai sunt date de intrare pentru `b`.");
+    }
+
+    #[test]
+    fn lenient_end_of_input() {
+        let mut parser = Parser::new(Lexer::new("citește a, b (număr natural)\n".to_string()));
+        let envb = Rc::new(RefCell::new(Environment::new()));
+        envb.borrow_mut().lenient = true;
+        envb.borrow_mut().input = Box::new(|_| Ok(0));
+        assert!(eval(parser.parse(), Some(Rc::clone(&envb))).is_ok());
+        assert!(*envb.borrow().syms["b"] == Object::Null);
+    }
+
+    #[test]
+    fn annotated_lists() {
+        let (envb, res) = run_with_input("citește a, b (numere naturale), c (caracter), d (șir de caractere)\n", "3\n4 xy\nab\n");
+        assert!(res.is_ok());
+        let env = envb.borrow();
+        assert!(*env.syms["a"] == Object::Int(3) && *env.syms["b"] == Object::Int(4));
+        assert!(*env.syms["c"] == Object::String("x".to_string()));
+        assert!(*env.syms["d"] == Object::String("y".to_string()));
+    }
+
+    #[test]
+    fn files() {
+        let path = std::env::temp_dir().join(format!("rolang-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let program = format!("scrie în \"{0}\" 3, ' ', 4\ncitește din \"{0}\" a\ncitește c\ncitește din \"{0}\" b\n", path);
+        let (envb, res) = run_with_input(&program, "9\n");
+        assert!(res.is_ok(), "{:?}", res.err());
+        let env = envb.borrow();
+        assert!(*env.syms["a"] == Object::Int(3) && *env.syms["b"] == Object::Int(4) && *env.syms["c"] == Object::Int(9));
+        std::fs::remove_file(path).unwrap();
+
+        let mut parser = Parser::new(Lexer::new(program));
+        let envb = Rc::new(RefCell::new(Environment::new()));
+        envb.borrow_mut().sandbox = true;
+        assert!(error_of(eval(parser.parse(), Some(envb))).to_string().contains("--izolat"));
+    }
+
+    #[test]
+    fn big_integers() {
+        let program = "citește n (număr natural)\nf <- 1\npentru i <- 1, 25 execută\n  f <- f * i\n■\nq <- [f / 7]\nr <- f % 1000007\nm <- n - f\nk <- [m / 1000]\n";
+        let (_, res) = run_with_input(program, "12345678901234567890123\n");
+        assert_eq!(error_of(res).to_string(), "Valoarea `12345678901234567890123` citită în `n` nu este un număr natural.");
+
+        let mut parser = Parser::new(Lexer::new(program.to_string()));
+        let envb = Rc::new(RefCell::new(Environment::new()));
+        envb.borrow_mut().arithmetic.integers = Integers::Big;
+        let mut data = Cursor::new("12345678901234567890123\n".to_string());
+        envb.borrow_mut().input = Box::new(move |buf| data.read_line(buf));
+        assert!(eval(parser.parse(), Some(Rc::clone(&envb))).is_ok());
+        let env = envb.borrow();
+        let get = |name: &str| env.syms[name].to_string();
+        assert_eq!(get("f"), "15511210043330985984000000");
+        assert_eq!(get("q"), "2215887149047283712000000");
+        assert_eq!(get("r"), "913534");
+        assert_eq!(get("m"), "-15498864364429751416109877");
+        assert_eq!(get("k"), "-15498864364429751416110");
+        assert!(ops::equal(&env.syms["r"], &Object::Int(913534)));
+    }
+
+    #[test]
+    fn cpp() {
+        let program = "citește n\na <- 3000000000\nb <- a + 0\np <- 2 ^ 40\nr <- 5.5 % 2\n";
+        let mut parser = Parser::new(Lexer::new(program.to_string()));
+        let envb = Rc::new(RefCell::new(Environment::new()));
+        envb.borrow_mut().arithmetic.integers = Integers::Cpp(32);
+        let mut data = Cursor::new("4294967297\n".to_string());
+        envb.borrow_mut().input = Box::new(move |buf| data.read_line(buf));
+        let res = eval(parser.parse(), Some(Rc::clone(&envb)));
+        assert_eq!(error_of(res).to_string(), "Cu `--cpp`, `%` se aplică doar numerelor întregi, ca în C++, nu lui 5.5 % 2.");
+        let env = envb.borrow();
+        let get = |name: &str| env.show(&env.syms[name], None);
+        assert_eq!((get("n"), get("a"), get("b"), get("p")), ("1".to_string(), "-1294967296".to_string(), "-1294967296".to_string(), "1.09951e+12".to_string()));
+    }
+
+    #[test]
+    fn for_loops() {
+        let get = |program: &str, name: &str| {
+            let (envb, res) = run_with_input(program, "");
+            assert!(res.is_ok(), "{}: {:?}", program, res.err().map(|e| e.to_string()));
+            envb.borrow().syms[name].to_string()
+        };
+        assert_eq!(get("s <- ''\npentru i <- 10, 1, -3 execută\n  s <- s + i + ' '\n■\n", "s"), "10 7 4 1 ");
+        assert_eq!(get("pentru i <- 10, 1, -3 execută\n■\n", "i"), "-2");
+        assert_eq!(get("s <- 0\npentru i <- 1, 3 execută\n■\n", "i"), "4");
+        assert_eq!(get("s <- 0\npentru i <- 5, 1 execută\n  s <- 1\n■\n", "s"), "0");
+        assert_eq!(get("s <- 0\npentru x <- 0, 1, 0.25 execută\n  s <- s + x\n■\n", "s"), "2.5");
+        assert_eq!(get("n <- 3\nk <- 0\npentru i <- 1, n execută\n  n <- 10\n  k <- k + 1\n■\n", "k"), "3");
+        assert_eq!(get("k <- 0\npentru i <- 1, 10 execută\n  i <- i * 2\n  k <- k + 1\n■\n", "i"), "15");
+        let (envb, res) = run_with_input("citește n (număr natural)\ns <- ''\npentru n <- 3, 0, -1 execută\n  s <- s + n\n■\n", "5\n");
+        assert!(res.is_ok(), "{:?}", res.err());
+        assert_eq!(envb.borrow().syms["s"].to_string(), "3210");
+        assert_eq!(envb.borrow().syms["n"].to_string(), "-1");
+
+        let error = |program: &str| error_of(run_with_input(program, "").1).to_string();
+        assert_eq!(error("pentru i <- 1, 5, 0 execută\n■\n"), "Pasul lui `pentru` nu poate fi 0.");
+        assert_eq!(error("pentru i <- 1, 'a' execută\n■\n"), "`pentru` parcurge doar numere, nu un șir de caractere (a).");
+    }
+
+    #[test]
+    fn else_if_chains() {
+        let program = "s <- ''\npentru a <- 1, 4 execută\n  dacă a = 1 atunci\n    s <- s + 'unu '\n  altfel dacă a = 2 atunci\n    s <- s + 'doi '\n  \
+                       altfel dacă a = 3 atunci s <- s + 'trei '\n  altfel\n    s <- s + 'mult '\n  ■\n■\ndacă a > 9 atunci\n  s <- 'x'\naltfel dacă a < 0 atunci\n  s <- 'y'\n■\n";
+        let (envb, res) = run_with_input(program, "");
+        assert!(res.is_ok(), "{:?}", res.err().map(|e| e.to_string()));
+        assert!(*envb.borrow().syms["s"] == Object::String("unu doi trei mult ".to_string()));
+    }
+
+    #[test]
+    fn loop_control() {
+        let get = |program: &str| {
+            let (envb, res) = run_with_input(program, "");
+            assert!(res.is_ok(), "{}: {:?}", program, res.err().map(|e| e.to_string()));
+            envb.borrow().syms["s"].to_string()
+        };
+        assert_eq!(get("s <- ''\npentru i <- 1, 10 execută\n  dacă i % 2 = 0 atunci\n    continuă\n  ■\n  dacă i > 6 atunci\n    întrerupe\n  ■\n  \
+                        s <- s + i\n■\ns <- s + '|' + i\n"), "135|7");
+        assert_eq!(get("s <- 0\ncât timp adevărat execută\n  s <- s + 1\n  dacă s = 3 atunci întrerupe\n  ■\n■\n"), "3");
+        assert_eq!(get("s <- ''\nk <- 4\nrepetă\n  k <- k - 1\n  dacă k = 2 atunci\n    continuă\n  ■\n  s <- s + k\npână când k = 0\n"), "310");
+        assert_eq!(get("s <- 0\nexecută\n  s <- s + 1\n  întrerupe\ncât timp adevărat\n"), "1");
+        assert_eq!(get("s <- 0\npentru i <- 1, 3 execută\n  pentru j <- 1, 3 execută\n    dacă j = 2 atunci\n      întrerupe\n    ■\n    s <- s + 1\n  ■\n■\n"), "3");
+
+        let mut parser = Parser::new(Lexer::new("întrerupe\ndacă adevărat atunci\n  continuă\n■\n".to_string()));
+        parser.parse();
+        let errors: Vec<_> = parser.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors, vec!["linia 1: `întrerupe` poate apărea doar într-o buclă.", "linia 3: `continuă` poate apărea doar într-o buclă."]);
+    }
+
+    #[test]
+    fn parallel_assignment() {
+        let (envb, res) = run_with_input("a <- 1\nb <- 2\na, b <- b, a\nc, d <- a + b, a - b\nc ↔ d\nx <- 5\nx <-> a\n", "");
+        assert!(res.is_ok(), "{:?}", res.err().map(|e| e.to_string()));
+        let get = |name: &str| envb.borrow().syms[name].to_string();
+        assert_eq!((get("a"), get("b"), get("c"), get("d"), get("x")), ("5".to_string(), "1".to_string(), "1".to_string(), "3".to_string(), "2".to_string()));
+
+        // O valoare invalidă oprește atribuirea înainte ca vreo variabilă să se schimbe.
+        let (envb, res) = run_with_input("citește n (număr natural)\nx <- 2.5\nn, x <- x, n\n", "3\n");
+        assert!(error_of(res).to_string().contains("nu poate primi valoarea 2.5"));
+        assert!(*envb.borrow().syms["x"] == Object::Float(2.5));
+
+        // Elementele de tablou: toți indicii se calculează înaintea oricărei atribuiri.
+        let (envb, res) = run_with_input("pentru i <- 1, 4 execută\n  v[i] <- i * 10\n■\nv[1] ↔ v[4]\ni <- 2\ni, v[i] <- 3, v[i] + 1\nm[1][2] <- v\n", "");
+        assert!(res.is_ok(), "{:?}", res.err().map(|e| e.to_string()));
+        let get = |name: &str| envb.borrow().syms[name].to_string();
+        assert_eq!((get("v"), get("i"), get("m")), ("{1: 40, 2: 21, 3: 30, 4: 10}".to_string(), "3".to_string(), "{1: {2: {1: 40, 2: 21, 3: 30, 4: 10}}}".to_string()));
+        let error = |program: &str| error_of(run_with_input(program, "").1).to_string();
+        assert_eq!(error("x <- 1\nv[1] <- 2\nx, v[1][1] <- 5, 3\n"), "`v` nu este un tablou, ci un număr întreg.");
+        assert_eq!(error("v[1.5] <- 2\n"), "Indicele unui tablou trebuie să fie un număr întreg, nu un număr real.");
+        let (envb, res) = run_with_input("x <- 1\nv[1] <- 2\nx, v[1][1] <- 5, 3\n", "");
+        assert!(res.is_err() && *envb.borrow().syms["x"] == Object::Int(1));
+        let (_, res) = run_with_input("scrie v[7]\n", "");
+        assert!(res.is_ok());
+
+        let mut parser = Parser::new(Lexer::new("a, b <- 1\na ↔ 3\npentru i, j <- 1, 2 execută\n■\n".to_string()));
+        parser.parse();
+        let errors: Vec<_> = parser.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors, vec!["linia 1: Atribuirea are 2 variabile, dar 1 valoare.", "linia 2: Se poate atribui doar unei variabile.",
+                                "linia 3: `pentru` are o singură variabilă contor."]);
     }
 }
@@ -1,23 +1,76 @@
 use std::{char, fmt::Display};
 
 use anyhow::{Result, anyhow};
+use num_bigint::BigInt;
+
+/// Tipul dintr-o adnotare ca `(număr natural)` sau `(numere reale)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VarType {
+    Natural,
+    Integer,
+    Real,
+    String,
+    Char,
+}
+
+/// Adnotările acceptate, cu forma de singular și de plural.
+const ANNOTATIONS: &[(&str, &str, VarType)] = &[
+    ("număr natural", "numere naturale", VarType::Natural),
+    ("natural", "naturale", VarType::Natural),
+    ("număr întreg", "numere întregi", VarType::Integer),
+    ("întreg", "întregi", VarType::Integer),
+    ("ref", "ref", VarType::Integer),
+    ("număr real", "numere reale", VarType::Real),
+    ("număr", "numere", VarType::Real),
+    ("real", "reale", VarType::Real),
+    ("șir de caractere", "șiruri de caractere", VarType::String),
+    ("caracter", "caractere", VarType::Char),
+];
+
+impl VarType {
+    fn forms(&self) -> (&'static str, &'static str) {
+        ANNOTATIONS.iter().find(|(_, _, t)| t == self).map(|(s, p, _)| (*s, *p)).unwrap()
+    }
+
+    pub fn plural(&self) -> &'static str {
+        self.forms().1
+    }
+}
+
+impl Display for VarType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.forms().0)
+    }
+}
+
+fn strip_diacritics(s: &str) -> String {
+    s.chars().map(|c| match c {
+        'ă' | 'â' => 'a',
+        'î' => 'i',
+        'ș' | 'ş' => 's',
+        'ț' | 'ţ' => 't',
+        c => c,
+    }).collect()
+}
 
 #[allow(dead_code)]
 #[derive(Debug, PartialEq)]
 pub enum Token {
     Identifier(String),
     Int(i64),
+    /// Un număr întreg care nu încape în `i64`.
+    BigInt(BigInt),
     Float(f64),
     String(String),
     True,
     False,
     Null,
 
-    CastUnsignedRef,
-    CastFloatRef,
-    CastRef,
+    Annotation(VarType),
 
     Comma,
+    /// `:` din `scrie x : 2`.
+    Colon,
     SColon,
 
     If,
@@ -28,21 +81,35 @@ pub enum Token {
     Repeat,
     While, // câttimp
     Until, // pânăcând
+    /// `întrerupe`, cu numele scris în program.
+    Break(String),
+    /// `continuă`, cu numele scris în program.
+    Continue(String),
 
     BlockEnd,
     FloorStart,
     FloorEnd,
+    /// `[` lipit de un nume sau de un alt indice, ca în `v[i]` sau `a[i][j]`.
+    IndexStart,
 
     LParen,
     RParen,
 
     Set,
+    /// `↔` sau `<->`, interschimbarea.
+    Swap,
 
     Add,
     Subtract,
     Multiply,
     Divide,
     Mod,
+    Power,
+    /// Un exponent scris cu indici superiori, ca în `x²` sau `2ⁿ`, transcris
+    /// cu caractere obișnuite.
+    Superscript(String),
+    Sqrt,
+    Pipe,
 
     Not,
     And,
@@ -64,15 +131,15 @@ impl Clone for Token {
         match self {
             Token::Identifier(x) => Token::Identifier(x.clone()),
             Token::Int(x) => Token::Int(x.clone()),
+            Token::BigInt(x) => Token::BigInt(x.clone()),
             Token::Float(x) => Token::Float(x.clone()),
             Token::String(x) => Token::String(x.clone()),
             Token::True => Token::True,
             Token::False => Token::False,
             Token::Null => Token::Null,
-            Token::CastUnsignedRef => Token::CastUnsignedRef,
-            Token::CastFloatRef => Token::CastFloatRef,
-            Token::CastRef => Token::CastRef,
+            Token::Annotation(x) => Token::Annotation(*x),
             Token::Comma => Token::Comma,
+            Token::Colon => Token::Colon,
             Token::SColon => Token::SColon,
             Token::If => Token::If,
             Token::Then => Token::Then,
@@ -82,17 +149,25 @@ impl Clone for Token {
             Token::Repeat => Token::Repeat,
             Token::While => Token::While,
             Token::Until => Token::Until,
+            Token::Break(x) => Token::Break(x.clone()),
+            Token::Continue(x) => Token::Continue(x.clone()),
             Token::BlockEnd => Token::BlockEnd,
             Token::FloorStart => Token::FloorStart,
             Token::FloorEnd => Token::FloorEnd,
+            Token::IndexStart => Token::IndexStart,
             Token::LParen => Token::LParen,
             Token::RParen => Token::RParen,
             Token::Set => Token::Set,
+            Token::Swap => Token::Swap,
             Token::Add => Token::Add,
             Token::Subtract => Token::Subtract,
             Token::Multiply => Token::Multiply,
             Token::Divide => Token::Divide,
             Token::Mod => Token::Mod,
+            Token::Power => Token::Power,
+            Token::Superscript(x) => Token::Superscript(x.clone()),
+            Token::Sqrt => Token::Sqrt,
+            Token::Pipe => Token::Pipe,
             Token::Not => Token::Not,
             Token::And => Token::And,
             Token::Or => Token::Or,
@@ -108,6 +183,16 @@ impl Clone for Token {
     }
 }
 
+/// Caracterul obișnuit corespunzător unui indice superior.
+fn superscript(ch: char) -> Option<char> {
+    Some(match ch {
+        '⁰' => '0', '¹' => '1', '²' => '2', '³' => '3', '⁴' => '4',
+        '⁵' => '5', '⁶' => '6', '⁷' => '7', '⁸' => '8', '⁹' => '9',
+        'ⁿ' => 'n', 'ⁱ' => 'i', '⁻' => '-',
+        _ => return None,
+    })
+}
+
 fn is_valid_romanian_character(ch: char) -> bool {
     match ch {
         'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | 'ă' | 'î' | 'ț' | 'â' | 'ș' | 'Ă' | 'Î' | 'Ț' | 'Â' | 'Ș' => true,
@@ -120,17 +205,17 @@ impl Display for Token {
         return match self {
             Token::Identifier(x) => write!(f, "Identifier({})", x),
             Token::Int(x) => write!(f, "Int({})", x),
+            Token::BigInt(x) => write!(f, "BigInt({})", x),
             Token::Float(x) => write!(f, "Float({})", x),
             Token::String(x) => write!(f, "String({})", x),
             Token::True => write!(f, "True"),
             Token::False => write!(f, "False"),
             Token::Null => write!(f, "Null"),
 
-            Token::CastUnsignedRef => write!(f, "CastUnsignedRef"),
-            Token::CastFloatRef => write!(f, "CastFloatRef"),
-            Token::CastRef => write!(f, "CastRef"),
+            Token::Annotation(x) => write!(f, "Annotation({})", x),
 
             Token::Comma => write!(f, "Comma"),
+            Token::Colon => write!(f, "Colon"),
             Token::SColon => write!(f, "SColon"),
 
             Token::If => write!(f, "If"),
@@ -141,21 +226,29 @@ impl Display for Token {
             Token::Repeat => write!(f, "Repeat"),
             Token::While => write!(f, "While"),
             Token::Until => write!(f, "Until"),
+            Token::Break(x) => write!(f, "Break({})", x),
+            Token::Continue(x) => write!(f, "Continue({})", x),
 
             Token::BlockEnd => write!(f, "BlockEnd"),
             Token::FloorStart => write!(f, "FloorStart"),
             Token::FloorEnd => write!(f, "FloorEnd"),
+            Token::IndexStart => write!(f, "IndexStart"),
 
             Token::LParen => write!(f, "("),
             Token::RParen => write!(f, ")"),
 
             Token::Set => write!(f, "Set"),
+            Token::Swap => write!(f, "Swap"),
 
             Token::Add => write!(f, "Add"),
             Token::Subtract => write!(f, "Subtract"),
             Token::Multiply => write!(f, "Multiply"),
             Token::Divide => write!(f, "Divide"),
             Token::Mod => write!(f, "Mod"),
+            Token::Power => write!(f, "Power"),
+            Token::Superscript(x) => write!(f, "Superscript({})", x),
+            Token::Sqrt => write!(f, "Sqrt"),
+            Token::Pipe => write!(f, "Pipe"),
 
             Token::Not => write!(f, "Not"),
             Token::And => write!(f, "And"),
@@ -174,20 +267,57 @@ impl Display for Token {
     }
 }
 
+/// Cuvintele cheie care se pot redenumi, pentru alte dialecte de pseudocod
+/// (de exemplu `break` în loc de `întrerupe`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dialect {
+    pub breaks: Vec<String>,
+    pub continues: Vec<String>,
+}
+
+impl Default for Dialect {
+    fn default() -> Dialect {
+        Dialect {
+            breaks: vec!["întrerupe".to_string()],
+            continues: vec!["continuă".to_string()],
+        }
+    }
+}
+
 pub struct Lexer {
     pos: usize,
     read_pos: usize,
     ch: char,
+    /// Caracterul dinaintea lui `ch`.
+    prev: char,
     input: String,
+    /// Linia caracterului curent, numerotată de la 1.
+    pub line: usize,
+    /// Linia la care începe ultimul token citit.
+    pub tok_line: usize,
+    /// Coloana (în caractere, de la 0) la care începe ultimul token citit.
+    pub tok_col: usize,
+    line_start: usize,
+    dialect: Dialect,
 }
 
 impl Lexer {
     pub fn new(input: String) -> Lexer {
+        Lexer::with_dialect(input, Dialect::default())
+    }
+
+    pub fn with_dialect(input: String, dialect: Dialect) -> Lexer {
         let mut lex = Lexer {
             pos: 0,
             read_pos: 0,
             ch: '\0',
+            prev: '\0',
             input,
+            line: 1,
+            tok_line: 1,
+            tok_col: 0,
+            line_start: 0,
+            dialect,
         };
 
         lex.read_char();
@@ -197,6 +327,8 @@ impl Lexer {
 
     pub fn next(&mut self) -> Result<Token> {
         self.skip_whitespace();
+        self.tok_line = self.line;
+        self.tok_col = self.pos - self.line_start;
 
         let mut should_skip = true;
 
@@ -204,11 +336,19 @@ impl Lexer {
             '"' => Token::String(self.read_string('"')),
             '\'' => Token::String(self.read_string('\'')),
             ',' => Token::Comma,
+            ':' => Token::Colon,
             '+' => Token::Add,
             '-' => Token::Subtract,
-            '*' => Token::Multiply,
-            '/' => Token::Divide,
+            '*' | '×' | '·' => Token::Multiply,
+            '/' | '÷' => Token::Divide,
             '%' => Token::Mod,
+            '^' => Token::Power,
+            '√' => Token::Sqrt,
+            '|' => Token::Pipe,
+            c if superscript(c).is_some() => {
+                should_skip = false;
+                Token::Superscript(self.read_superscript())
+            }
             '=' => Token::Equal,
             '≠' => Token::NotEqual,
             ';' | '\n' => Token::SColon,
@@ -225,43 +365,27 @@ impl Lexer {
                 if self.peek() == ']' {
                     self.read_char();
                     Token::BlockEnd
+                } else if is_valid_romanian_character(self.prev) || self.prev == ']' {
+                    Token::IndexStart
                 } else {
                     Token::FloorStart
                 }
             }
             ']' => Token::FloorEnd,
-            '(' => {
-                let rest = self.input.chars().skip(self.pos).collect::<String>();
-                let rest = rest.trim();
-                if rest.starts_with("(număr natural)") {
-                    for _ in 1..15 {
-                        self.read_char()
-                    }
-                    Token::CastUnsignedRef
-                } else if rest.starts_with("(număr)") {
-                    for _ in 1..7 {
-                        self.read_char()
-                    }
-                    Token::CastFloatRef
-                } else if rest.starts_with("(număr real)") {
-                    for _ in 1..12 {
-                        self.read_char()
-                    }
-                    Token::CastFloatRef
-                } else if rest.starts_with("(ref)") {
-                    for _ in 1..5 {
-                        self.read_char()
-                    }
-                    Token::CastRef
-                } else {
-                    Token::LParen
-                }
+            '(' => match self.read_annotation()? {
+                Some(t) => Token::Annotation(t),
+                None => Token::LParen,
             },
             ')' => Token::RParen,
             '<' => {
                 if self.peek() == '-' {
                     self.read_char();
-                    Token::Set
+                    if self.peek() == '>' {
+                        self.read_char();
+                        Token::Swap
+                    } else {
+                        Token::Set
+                    }
                 } else if self.peek() == '=' {
                     self.read_char();
                     Token::LessThanEqual
@@ -278,6 +402,7 @@ impl Lexer {
                     Token::GreaterThan
                 }
             },
+            '↔' => Token::Swap,
             '≤' => Token::LessThanEqual,
             '≥' => Token::GreaterThanEqual,
             'a'..='z' | 'A'..='Z' | '_' | 'ă' | 'î' | 'ț' | 'ș' | 'Ă' | 'Î' | 'Ț' | 'Ș' => {
@@ -293,6 +418,7 @@ impl Lexer {
                     "cat timp" => { return Err(anyhow!("Folosește diacitice, este `cât timp`, nu `cat timp`!")); },
                     "pana cand" | "pâna cand" | "până cand" | "pană când" | "pana când" | "pâna când" | "pană cand" => { return Err(anyhow!("Folosește diacitice, este `până cand`, nu `pana cand`!")); },
                     "citeste" => { return Err(anyhow!("Folosește diacitice, este `citește`, nu `citeste`!")); },
+                    "intrerupe" if self.dialect.breaks.iter().any(|b| b == "întrerupe") => { return Err(anyhow!("Folosește diacitice, este `întrerupe`, nu `intrerupe`!")); },
                     _ => ()
                 }
 
@@ -313,17 +439,21 @@ impl Lexer {
                     "not" => Token::Not,
                     "și" => Token::And,
                     "sau" => Token::Or,
+                    x if self.dialect.breaks.iter().any(|b| b == x) => Token::Break(ident),
+                    x if self.dialect.continues.iter().any(|c| c == x) => Token::Continue(ident),
                     _ => Token::Identifier(ident),
                 })
             },
             '0'..='9' => {
-                if self.ch == '0' && self.peek().is_numeric() {
-                    return Err(anyhow!("Invalid number literal"));
-                }
-
+                // Literalul greșit se citește întreg, ca următorul token să
+                // înceapă după el.
                 let num = self.read_number();
                 should_skip = false;
 
+                if num.starts_with('0') && num[1..].starts_with(|c: char| c.is_ascii_digit()) {
+                    return Err(anyhow!("Invalid number literal"));
+                }
+
                 if num.contains('.') {
                     if let Ok(f) = num.parse::<f64>() {
                         Token::Float(f)
@@ -333,13 +463,19 @@ impl Lexer {
                 } else {
                     if let Ok(i) = num.parse::<i64>() {
                         Token::Int(i)
+                    } else if let Ok(i) = num.parse::<BigInt>() {
+                        Token::BigInt(i)
                     } else {
                         return Err(anyhow!("Invalid integer literal"));
                     }
                 }
             },
             '\0' => Token::EOF,
-            _ => unreachable!("Caracter invalid: {}", self.ch),
+            _ => {
+                let ch = self.ch;
+                self.read_char();
+                return Err(anyhow!("Caracter invalid: `{}`", ch));
+            }
         };
 
         if should_skip {
@@ -348,9 +484,42 @@ impl Lexer {
         return Ok(tok);
     }
 
+    /// Recunoaște o adnotare de tip între paranteze. O paranteză care conține
+    /// doar cuvinte este adnotare dacă are mai multe cuvinte sau începe ca una
+    /// cunoscută; altfel, ca `(x)` sau `(a sau b)`, este o expresie.
+    fn read_annotation(&mut self) -> Result<Option<VarType>> {
+        let inner: String = self.input.chars().skip(self.pos + 1).take_while(|c| *c != ')' && *c != '\n').collect();
+        if self.input.chars().nth(self.pos + 1 + inner.chars().count()) != Some(')')
+            || !inner.chars().all(|c| c.is_alphabetic() || c == ' ') {
+            return Ok(None);
+        }
+        let words: Vec<_> = inner.split_whitespace().map(|w| w.to_lowercase()).collect();
+        let is_head = |w: &str| ANNOTATIONS.iter().any(|(s, p, _)| {
+            let head = |x: &str| strip_diacritics(x.split(' ').next().unwrap());
+            head(s) == strip_diacritics(w) || head(p) == strip_diacritics(w)
+        });
+        if words.is_empty() || (words.len() == 1 && !is_head(&words[0])) || words.iter().any(|w| matches!(w.as_str(), "și" | "sau" | "not")) {
+            return Ok(None);
+        }
+
+        for _ in 0..=inner.chars().count() {
+            self.read_char();
+        }
+        let text = words.join(" ");
+        if let Some((_, _, t)) = ANNOTATIONS.iter().find(|(s, p, _)| *s == text || *p == text) {
+            return Ok(Some(*t));
+        }
+        self.read_char();
+        if let Some((s, p, _)) = ANNOTATIONS.iter().find(|(s, p, _)| strip_diacritics(s) == strip_diacritics(&text) || strip_diacritics(p) == strip_diacritics(&text)) {
+            let correct = if strip_diacritics(s) == strip_diacritics(&text) { s } else { p };
+            return Err(anyhow!("Folosește diacritice, este `({})`, nu `({})`!", correct, text));
+        }
+        Err(anyhow!("Adnotare necunoscută: `({})`. Se acceptă număr natural, număr întreg, număr real, șir de caractere și caracter, la singular sau plural.", text))
+    }
+
     fn read_number(&mut self) -> String {
         let start_pos = self.pos;
-        while self.ch.is_numeric() || self.ch == '.' {
+        while self.ch.is_ascii_digit() || self.ch == '.' {
             self.read_char();
         }
         self.input.chars().skip(start_pos).take(self.pos - start_pos).collect()
@@ -399,6 +568,15 @@ impl Lexer {
         return self.input.chars().skip(position).take(self.pos - position).collect::<String>().replace("\\n", "\n").replace("\\r", "\r").replace("\\t", "\t").replace("\\e", "\x1b");
     }
 
+    fn read_superscript(&mut self) -> String {
+        let mut exponent = String::new();
+        while let Some(c) = superscript(self.ch) {
+            exponent.push(c);
+            self.read_char();
+        }
+        exponent
+    }
+
     fn skip_whitespace(&mut self) {
         while (self.ch.is_whitespace() && self.ch != '\n') || matches!(self.ch, '│' | '└' | '┌') {
             self.read_char();
@@ -406,6 +584,11 @@ impl Lexer {
     }
 
     fn read_char(&mut self) {
+        self.prev = self.ch;
+        if self.ch == '\n' {
+            self.line += 1;
+            self.line_start = self.read_pos;
+        }
         if self.read_pos >= self.input.chars().count() {
             self.ch = '\0';
         } else {
@@ -428,7 +611,7 @@ impl Lexer {
 mod tests {
     use anyhow::Result;
 
-    use super::{Lexer, Token};
+    use super::{Dialect, Lexer, Token, VarType};
 
     #[test]
     fn lex_basic() -> Result<()> {
@@ -518,5 +701,109 @@ mod tests {
 
         Ok(())
     }
-}
 
+    #[test]
+    fn lex_big_int() -> Result<()> {
+        let mut lex = Lexer::new(String::from("9223372036854775807 9223372036854775808"));
+        assert_eq!(lex.next()?, Token::Int(i64::MAX));
+        assert_eq!(lex.next()?, Token::BigInt("9223372036854775808".parse().unwrap()));
+        Ok(())
+    }
+
+    #[test]
+    fn lex_math() -> Result<()> {
+        let mut lex = Lexer::new(String::from("√x² × 2·y ÷ |z|^3 + 10ⁿ⁻¹"));
+        let tokens = vec![
+            Token::Sqrt,
+            Token::Identifier("x".to_string()),
+            Token::Superscript("2".to_string()),
+            Token::Multiply,
+            Token::Int(2),
+            Token::Multiply,
+            Token::Identifier("y".to_string()),
+            Token::Divide,
+            Token::Pipe,
+            Token::Identifier("z".to_string()),
+            Token::Pipe,
+            Token::Power,
+            Token::Int(3),
+            Token::Add,
+            Token::Int(10),
+            Token::Superscript("n-1".to_string()),
+            Token::EOF,
+        ];
+        for token in tokens {
+            assert_eq!(lex.next()?, token);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn lex_annotation() -> Result<()> {
+        let input = String::from("(număr natural) (Numere  Reale) (șir de caractere) (x) (caracter) (ref)");
+        let mut lex = Lexer::new(input);
+
+        let tokens = vec![
+            Token::Annotation(VarType::Natural),
+            Token::Annotation(VarType::Real),
+            Token::Annotation(VarType::String),
+            Token::LParen,
+            Token::Identifier(String::from("x")),
+            Token::RParen,
+            Token::Annotation(VarType::Char),
+            Token::Annotation(VarType::Integer),
+            Token::EOF,
+        ];
+
+        for token in tokens {
+            let tok = lex.next()?;
+            assert_eq!(tok, token);
+        }
+
+        let mut lex = Lexer::new(String::from("(număr complex) (numar natural) x"));
+        assert!(lex.next().unwrap_err().to_string().starts_with("Adnotare necunoscută: `(număr complex)`"));
+        assert_eq!(lex.next().unwrap_err().to_string(), "Folosește diacritice, este `(număr natural)`, nu `(numar natural)`!");
+        assert_eq!(lex.next()?, Token::Identifier(String::from("x")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn lex_dialect() -> Result<()> {
+        let mut lex = Lexer::new(String::from("întrerupe Continuă break"));
+        assert_eq!(lex.next()?, Token::Break("întrerupe".to_string()));
+        assert_eq!(lex.next()?, Token::Continue("continuă".to_string()));
+        assert_eq!(lex.next()?, Token::Identifier("break".to_string()));
+
+        let dialect = Dialect { breaks: vec!["break".to_string()], continues: vec!["continue".to_string(), "sari".to_string()] };
+        let mut lex = Lexer::with_dialect(String::from("break sari întrerupe"), dialect);
+        assert_eq!(lex.next()?, Token::Break("break".to_string()));
+        assert_eq!(lex.next()?, Token::Continue("sari".to_string()));
+        assert_eq!(lex.next()?, Token::Identifier("întrerupe".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn lex_swap() -> Result<()> {
+        let mut lex = Lexer::new(String::from("a ↔ b<->c <- d"));
+        let tokens = [Token::Identifier("a".to_string()), Token::Swap, Token::Identifier("b".to_string()), Token::Swap,
+                      Token::Identifier("c".to_string()), Token::Set, Token::Identifier("d".to_string())];
+        for token in tokens {
+            assert_eq!(lex.next()?, token);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn lex_index() -> Result<()> {
+        let mut lex = Lexer::new(String::from("v[i] [x] a[1][j]"));
+        let tokens = [Token::Identifier("v".to_string()), Token::IndexStart, Token::Identifier("i".to_string()), Token::FloorEnd,
+                      Token::FloorStart, Token::Identifier("x".to_string()), Token::FloorEnd,
+                      Token::Identifier("a".to_string()), Token::IndexStart, Token::Int(1), Token::FloorEnd,
+                      Token::IndexStart, Token::Identifier("j".to_string()), Token::FloorEnd];
+        for token in tokens {
+            assert_eq!(lex.next()?, token);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use anyhow::{Result, anyhow};
+
+use crate::builtins::{is_builtin, lookup};
+use crate::formatter::expr as source;
+use crate::parser::{ASTNode, ASTNodeValue};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Lint {
+    /// Variabilă citită înainte de orice atribuire sau `citește`.
+    Uninit,
+    /// Variabilă care primește o valoare, dar nu este folosită.
+    Unused,
+    /// `x = 1` ca instrucțiune, probabil în loc de `x ← 1`.
+    EqAsSet,
+    /// Buclă ale cărei variabile din condiție nu se schimbă în corp.
+    LoopInvariant,
+    /// `altfel` după o condiție mereu adevărată.
+    UnreachableElse,
+    /// Condiție cu valoare constantă.
+    ConstCond,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 6] = [Lint::Uninit, Lint::Unused, Lint::EqAsSet, Lint::LoopInvariant, Lint::UnreachableElse, Lint::ConstCond];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lint::Uninit => "neinițializată",
+            Lint::Unused => "nefolosită",
+            Lint::EqAsSet => "egal-atribuire",
+            Lint::LoopInvariant => "buclă-fixă",
+            Lint::UnreachableElse => "altfel-inaccesibil",
+            Lint::ConstCond => "condiție-constantă",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Lint> {
+        let english = ["uninit", "unused", "eq-as-set", "loop-invariant", "unreachable-else", "const-cond"];
+        Lint::ALL.iter().zip(english)
+            .find(|(lint, en)| lint.code() == name || *en == name)
+            .map(|(lint, _)| *lint)
+            .ok_or_else(|| anyhow!("Avertisment necunoscut: `{}`.", name))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// Nivelul fiecărui avertisment; implicit toate sunt `Warn`.
+#[derive(Default)]
+pub struct Config {
+    levels: HashMap<Lint, Level>,
+}
+
+impl Config {
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.levels.insert(lint, level);
+    }
+
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels.get(&lint).copied().unwrap_or(Level::Warn)
+    }
+}
+
+#[derive(Debug)]
+pub struct Warning {
+    pub lint: Lint,
+    pub line: usize,
+    pub message: String,
+}
+
+/// Valoarea de adevăr a unei condiții, dacă se poate afla fără a rula
+/// programul.
+fn constant(node: &ASTNode) -> Option<bool> {
+    let number = |n: &ASTNode| match n.value {
+        ASTNodeValue::Int(x) => Some(x as f64),
+        ASTNodeValue::Float(x) => Some(x),
+        _ => None,
+    };
+    match &node.value {
+        ASTNodeValue::Bool(x) => Some(*x),
+        ASTNodeValue::Int(x) => Some(*x != 0),
+        ASTNodeValue::BigInt(_) => Some(true),
+        ASTNodeValue::Float(x) => Some(*x != 0.0),
+        ASTNodeValue::String(_) => Some(true),
+        ASTNodeValue::Null => Some(false),
+        ASTNodeValue::Not => constant(&node.children[0]).map(|x| !x),
+        ASTNodeValue::And => Some(constant(&node.children[0])? && constant(&node.children[1])?),
+        ASTNodeValue::Or => Some(constant(&node.children[0])? || constant(&node.children[1])?),
+        ASTNodeValue::Equal | ASTNodeValue::NotEqual | ASTNodeValue::LessThan |
+        ASTNodeValue::GreaterThan | ASTNodeValue::LessThanEqual | ASTNodeValue::GreaterThanEqual => {
+            let (left, right) = (&node.children[0], &node.children[1]);
+            if let (Some(a), Some(b)) = (number(left), number(right)) {
+                return Some(match node.value {
+                    ASTNodeValue::Equal => a == b,
+                    ASTNodeValue::NotEqual => a != b,
+                    ASTNodeValue::LessThan => a < b,
+                    ASTNodeValue::GreaterThan => a > b,
+                    ASTNodeValue::LessThanEqual => a <= b,
+                    _ => a >= b,
+                });
+            }
+            // `x = x`, `x ≤ x`: aceeași expresie, fără apeluri, în ambele părți.
+            if !has_call(left) && source(left) == source(right) {
+                return Some(matches!(node.value, ASTNodeValue::Equal | ASTNodeValue::LessThanEqual | ASTNodeValue::GreaterThanEqual));
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+fn has_call(node: &ASTNode) -> bool {
+    matches!(node.value, ASTNodeValue::FunctionCall(_)) || node.children.iter().any(|c| has_call(c))
+}
+
+/// Dacă `node` conține un `întrerupe` care iese din bucla lui, nu dintr-una
+/// interioară.
+fn breaks(node: &ASTNode) -> bool {
+    match node.value {
+        ASTNodeValue::Break(_) => true,
+        ASTNodeValue::For | ASTNodeValue::While | ASTNodeValue::Until | ASTNodeValue::Execute => false,
+        _ => node.children.iter().any(|c| breaks(c)),
+    }
+}
+
+fn identifiers(node: &ASTNode, out: &mut BTreeSet<String>) {
+    if let ASTNodeValue::Identifier(x) = &node.value && !is_builtin(x) {
+        out.insert(x.clone());
+    }
+    for child in &node.children {
+        identifiers(child, out);
+    }
+}
+
+/// Variabila în care scrie o atribuire: `v` pentru `v` sau `v[i][j]`.
+fn target_name(mut target: &ASTNode) -> Option<&String> {
+    while target.value == ASTNodeValue::Index {
+        target = target.children.first()?;
+    }
+    match &target.value {
+        ASTNodeValue::Identifier(x) => Some(x),
+        _ => None,
+    }
+}
+
+/// Variabilele care primesc o valoare oriunde în `node`.
+fn assigned(node: &ASTNode, out: &mut BTreeSet<String>) {
+    match &node.value {
+        ASTNodeValue::Set | ASTNodeValue::Swap => {
+            let targets = if node.value == ASTNodeValue::Set { node.assignment().0 } else { &node.children[..] };
+            for target in targets {
+                if let Some(x) = target_name(target) {
+                    out.insert(x.clone());
+                }
+            }
+        }
+        ASTNodeValue::FunctionCall(name) if lookup(name).is_some_and(|b| b.assigns()) => {
+            for arg in &node.children {
+                if let ASTNodeValue::Identifier(x) | ASTNodeValue::Cast(x, _) = &arg.value {
+                    out.insert(x.clone());
+                }
+            }
+        }
+        _ => (),
+    }
+    for child in &node.children {
+        assigned(child, out);
+    }
+}
+
+#[derive(Default)]
+struct Checker {
+    warnings: Vec<Warning>,
+    /// Variabilele atribuite până la punctul curent, în ordinea textului.
+    defined: HashSet<String>,
+    read: HashSet<String>,
+    /// Prima atribuire a fiecărei variabile.
+    first_set: Vec<(String, usize)>,
+    counters: HashSet<String>,
+    reported: HashSet<String>,
+}
+
+impl Checker {
+    fn warn(&mut self, lint: Lint, line: usize, message: String) {
+        self.warnings.push(Warning { lint, line, message });
+    }
+
+    fn assign(&mut self, name: &str, line: usize) {
+        if self.defined.insert(name.to_string()) {
+            self.first_set.push((name.to_string(), line));
+        }
+    }
+
+    fn expr(&mut self, node: &ASTNode, line: usize) {
+        match &node.value {
+            ASTNodeValue::Identifier(x) if !is_builtin(x) => {
+                self.read.insert(x.clone());
+                if !self.defined.contains(x) && self.reported.insert(x.clone()) {
+                    self.warn(Lint::Uninit, line, format!("Variabila `{}` este folosită înainte să primească o valoare.", x));
+                }
+            }
+            ASTNodeValue::Set => self.stmt(node, line),
+            _ => {
+                for child in &node.children {
+                    self.expr(child, line);
+                }
+            }
+        }
+    }
+
+    fn condition(&mut self, cond: &ASTNode, line: usize, loop_body: Option<&ASTNode>, until: bool) {
+        match (constant(cond), loop_body) {
+            // `cât timp adevărat execută ... întrerupe ...` se oprește din corp.
+            (Some(value), Some(body)) if value != until && breaks(body) => (),
+            (Some(value), Some(_)) => {
+                let (runs, stops) = if until { ("falsă", "adevărată") } else { ("adevărată", "falsă") };
+                let what = if value != until { format!("mereu {}: bucla nu se oprește", runs) } else { format!("mereu {}: corpul nu se execută", stops) };
+                self.warn(Lint::ConstCond, line, format!("Condiția buclei este {}.", what));
+            }
+            (None, Some(body)) if !has_call(cond) && !breaks(body) => {
+                let mut vars = BTreeSet::new();
+                identifiers(cond, &mut vars);
+                let mut changed = BTreeSet::new();
+                assigned(body, &mut changed);
+                assigned(cond, &mut changed);
+                if !vars.is_empty() && vars.is_disjoint(&changed) {
+                    let names: Vec<_> = vars.iter().map(|v| format!("`{}`", v)).collect();
+                    self.warn(Lint::LoopInvariant, line, format!("Variabilele din condiția buclei ({}) nu se schimbă în corpul ei.", names.join(", ")));
+                }
+            }
+            _ => (),
+        }
+        self.expr(cond, line);
+    }
+
+    fn for_header(&mut self, node: &ASTNode, line: usize) {
+        let set = &node.children[0];
+        self.expr(&set.children[1], line);
+        if let ASTNodeValue::Identifier(x) = &set.children[0].value {
+            self.assign(x, line);
+            self.counters.insert(x.clone());
+        }
+        for child in node.children.iter().skip(1).filter(|c| c.value != ASTNodeValue::Program) {
+            self.expr(child, line);
+        }
+    }
+
+    fn stmt(&mut self, node: &ASTNode, line: usize) {
+        let line = if node.line > 0 { node.line } else { line };
+        match &node.value {
+            ASTNodeValue::Program => {
+                for child in &node.children {
+                    self.stmt(child, line);
+                }
+            }
+            ASTNodeValue::Set => {
+                let (targets, values) = node.assignment();
+                for value in values {
+                    self.expr(value, line);
+                }
+                for target in targets {
+                    // Indicii din `v[i] ← x` se citesc.
+                    let mut index = target.as_ref();
+                    while index.value == ASTNodeValue::Index && let [array, i] = index.children.as_slice() {
+                        self.expr(i, line);
+                        index = array;
+                    }
+                    if let Some(x) = target_name(target) {
+                        self.assign(x, line);
+                    }
+                }
+            }
+            ASTNodeValue::Swap => {
+                for target in &node.children {
+                    self.expr(target, line);
+                }
+            }
+            ASTNodeValue::FunctionCall(name) if lookup(name).is_some_and(|b| b.assigns()) => {
+                let mut names = BTreeSet::new();
+                assigned(node, &mut names);
+                for name in names {
+                    self.assign(&name, line);
+                }
+            }
+            ASTNodeValue::Equal if matches!(node.children[0].value, ASTNodeValue::Identifier(_)) => {
+                self.warn(Lint::EqAsSet, line, format!("`{}` doar compară; pentru atribuire folosește `{}`.",
+                    source(node), source(node).replacen(" = ", " ← ", 1)));
+                self.expr(node, line);
+            }
+            ASTNodeValue::If => {
+                let branches = node.children.chunks_exact(2);
+                let other = branches.remainder().first();
+                for (i, branch) in branches.enumerate() {
+                    let (cond, body) = (&branch[0], &branch[1]);
+                    let line = if cond.line > 0 { cond.line } else { line };
+                    match (constant(cond), node.children.len() > 2 * i + 2) {
+                        (Some(true), true) => self.warn(Lint::UnreachableElse, line, "Condiția este mereu adevărată, ramura `altfel` nu se execută niciodată.".to_string()),
+                        (Some(true), _) => self.warn(Lint::ConstCond, line, "Condiția este mereu adevărată.".to_string()),
+                        (Some(false), _) => self.warn(Lint::ConstCond, line, "Condiția este mereu falsă, ramura `atunci` nu se execută niciodată.".to_string()),
+                        _ => (),
+                    }
+                    self.expr(cond, line);
+                    self.stmt(body, line);
+                }
+                if let Some(other) = other {
+                    self.stmt(other, line);
+                }
+            }
+            ASTNodeValue::For => {
+                self.for_header(node, line);
+                if let Some(body) = node.children.last() && body.value == ASTNodeValue::Program {
+                    self.stmt(body, line);
+                }
+            }
+            ASTNodeValue::While | ASTNodeValue::Until => {
+                self.condition(&node.children[0], line, node.children.get(1).map(|b| b.as_ref()), node.value == ASTNodeValue::Until);
+                if let Some(body) = node.children.get(1) {
+                    self.stmt(body, line);
+                }
+            }
+            ASTNodeValue::Execute => {
+                self.stmt(&node.children[0], line);
+                if let Some(header) = node.children.get(1) {
+                    let line = if header.line > 0 { header.line } else { line };
+                    match header.value {
+                        ASTNodeValue::For => self.for_header(header, line),
+                        _ => self.condition(&header.children[0], line, Some(&node.children[0]), header.value == ASTNodeValue::Until),
+                    }
+                }
+            }
+            _ => self.expr(node, line),
+        }
+    }
+}
+
+/// Caută greșelile frecvente din program. Avertismentele sunt ordonate după
+/// linie.
+pub fn lint(root: &ASTNode) -> Vec<Warning> {
+    let mut checker = Checker::default();
+    checker.stmt(root, 0);
+    for (name, line) in std::mem::take(&mut checker.first_set) {
+        if !checker.read.contains(&name) && !checker.counters.contains(&name) {
+            checker.warn(Lint::Unused, line, format!("Variabila `{}` primește o valoare, dar nu este folosită.", name));
+        }
+    }
+    checker.warnings.sort_by_key(|w| w.line);
+    checker.warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lint, Lint};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn warnings(input: &str) -> Vec<(Lint, usize)> {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let root = parser.parse();
+        assert!(parser.errors.is_empty(), "{:?}", parser.errors);
+        lint(&root).into_iter().map(|w| (w.lint, w.line)).collect()
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(warnings("citește n (număr natural)\ns <- 0\npentru i <- 1, n execută\n  s <- s + i\n■\nscrie s\n"), vec![]);
+        assert_eq!(warnings("a <- 1\nscrie b\nb <- 2\nc = a\nd <- 3\n"), vec![(Lint::Uninit, 2), (Lint::EqAsSet, 4), (Lint::Uninit, 4), (Lint::Unused, 5)]);
+        assert_eq!(warnings("v[i] <- 1\ni <- 1\nv[i] ↔ v[2]\nscrie v[1]\n"), vec![(Lint::Uninit, 1)]);
+    }
+
+    #[test]
+    fn conditions() {
+        let input = "citește x (număr natural)\ncât timp x > 0 execută\n  scrie x\n■\ndacă x = x atunci\n  scrie 1\naltfel\n  scrie 2\n■\n\
+                     până când 1 < 2 execută\n  x <- x - 1\n■\nexecută\n  x <- x - 1\ncât timp x > 0\n";
+        assert_eq!(warnings(input), vec![(Lint::LoopInvariant, 2), (Lint::UnreachableElse, 5), (Lint::ConstCond, 10)]);
+    }
+
+    #[test]
+    fn else_if_conditions() {
+        let input = "citește x (număr natural)\ndacă x > 5 atunci\n  scrie 1\naltfel dacă 2 > 1 atunci\n  scrie 2\naltfel\n  scrie 3\n■\n";
+        assert_eq!(warnings(input), vec![(Lint::UnreachableElse, 4)]);
+    }
+
+    #[test]
+    fn loops_with_break() {
+        let input = "k <- 0\ncât timp adevărat execută\n  k <- k + 1\n  dacă k > 3 atunci\n    întrerupe\n  ■\n■\n\
+                     cât timp k > 0 execută\n  scrie k\n  întrerupe\n■\ncât timp k > 0 execută\n  pentru i <- 1, 2 execută\n    întrerupe\n  ■\n■\n";
+        assert_eq!(warnings(input), vec![(Lint::LoopInvariant, 12)]);
+    }
+}
@@ -0,0 +1,450 @@
+use std::{collections::{BTreeSet, HashMap}, io::{BufRead, Write}};
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::builtins::{BUILTINS, is_builtin, lookup};
+use crate::formatter::format_source;
+use crate::lexer::{Dialect, Lexer, Token};
+use crate::lint::lint;
+use crate::ops::Arithmetic;
+use crate::types::infer_with;
+use crate::parser::{ASTNode, ParseError, Parser};
+use crate::rpc::{read_message, write_message};
+
+/// Cuvintele cheie fixe; `întrerupe` și `continuă` vin din dialect.
+const KEYWORDS: &[&str] = &[
+    "dacă", "atunci", "altfel", "pentru", "execută", "repetă", "cât timp", "până când",
+    "și", "sau", "not", "adevărat", "fals", "nul",
+];
+
+const SEVERITY_ERROR: i64 = 1;
+const SEVERITY_WARNING: i64 = 2;
+
+const COMPLETION_FUNCTION: i64 = 3;
+const COMPLETION_VARIABLE: i64 = 6;
+const COMPLETION_KEYWORD: i64 = 14;
+
+/// Un nume din sursă, cu poziția lui (linie și coloană de la 0, în caractere).
+struct Name {
+    name: String,
+    line: usize,
+    col: usize,
+    len: usize,
+    /// Numele primește aici o valoare (`x ←`, `a, b ←`, `v[i] ←`, `a ↔ b`,
+    /// `citește x`, `pentru x ←`).
+    assigned: bool,
+}
+
+/// Numele din sursă, în ordine. Apelurile funcțiilor predefinite (`citește`,
+/// `scrie`) se recunosc după primul token de pe linie.
+fn names(text: &str, dialect: &Dialect) -> Vec<Name> {
+    let mut lexer = Lexer::with_dialect(text.to_string(), dialect.clone());
+    let mut names: Vec<Name> = Vec::new();
+    let mut reading = false;
+    let mut stmt_start = true;
+    let mut prev_ident = false;
+    // Numele instrucțiunii curente din afara indicilor, care pot primi o
+    // valoare, și dacă urmează al doilea nume din `a ↔ b`.
+    let mut targets: Vec<usize> = Vec::new();
+    let mut swapping = false;
+    // Parantezele drepte deschise: `true` pentru un indice `v[`, `false`
+    // pentru partea întreagă `[`.
+    let mut brackets: Vec<bool> = Vec::new();
+    // Cuvântul `din` / `în` care poate urma după funcție.
+    let mut file_keyword: Option<&str> = None;
+    loop {
+        let token = match lexer.next() {
+            Ok(Token::EOF) => break,
+            Ok(t) => t,
+            Err(_) => continue,
+        };
+        if token == Token::Set && prev_ident {
+            for &i in &targets {
+                names[i].assigned = true;
+            }
+        }
+        if token == Token::Swap && prev_ident && let Some(&last) = targets.last() {
+            names[last].assigned = true;
+            swapping = true;
+        }
+        prev_ident = false;
+        let keyword = file_keyword.take();
+        match &token {
+            Token::Identifier(name) if stmt_start && is_builtin(name) => {
+                let builtin = lookup(name).unwrap();
+                reading = builtin.assigns();
+                file_keyword = builtin.file.map(|(k, _)| k);
+            }
+            Token::Identifier(name) if keyword == Some(name.as_str()) => (),
+            Token::Identifier(name) if brackets.contains(&true) => {
+                let len = name.chars().count();
+                names.push(Name { name: name.clone(), line: lexer.tok_line - 1, col: lexer.tok_col, len, assigned: false });
+            }
+            Token::Identifier(name) => {
+                let len = name.chars().count();
+                targets.push(names.len());
+                names.push(Name { name: name.clone(), line: lexer.tok_line - 1, col: lexer.tok_col, len, assigned: reading || swapping });
+                prev_ident = true;
+                swapping = false;
+            }
+            Token::IndexStart | Token::FloorStart => brackets.push(token == Token::IndexStart),
+            Token::FloorEnd => {
+                prev_ident = brackets.pop() == Some(true) && !brackets.contains(&true);
+            }
+            Token::SColon => {
+                reading = false;
+                swapping = false;
+            }
+            _ => (),
+        }
+        stmt_start = matches!(token, Token::SColon | Token::Then | Token::Else | Token::Execute | Token::BlockEnd);
+        if stmt_start || token == Token::For {
+            targets.clear();
+        }
+    }
+    names
+}
+
+fn parse(text: &str, dialect: &Dialect) -> (Box<ASTNode>, Vec<ParseError>) {
+    let mut parser = Parser::new(Lexer::with_dialect(text.to_string(), dialect.clone()));
+    let root = parser.parse();
+    (root, parser.errors)
+}
+
+/// Lungimea în unități UTF-16, cum numără LSP coloanele.
+fn utf16_len(s: &str) -> usize {
+    s.chars().map(char::len_utf16).sum()
+}
+
+fn position(text: &str, line: usize, col: usize) -> Value {
+    let prefix: String = text.lines().nth(line).unwrap_or_default().chars().take(col).collect();
+    json!({ "line": line, "character": utf16_len(&prefix) })
+}
+
+fn range(text: &str, name: &Name) -> Value {
+    json!({ "start": position(text, name.line, name.col), "end": position(text, name.line, name.col + name.len) })
+}
+
+fn end_of(text: &str) -> Value {
+    let line = text.matches('\n').count();
+    let last = text.rsplit('\n').next().unwrap_or_default();
+    json!({ "line": line, "character": utf16_len(last) })
+}
+
+struct Server {
+    output: Box<dyn Write>,
+    docs: HashMap<String, String>,
+    dialect: Dialect,
+    arithmetic: Arithmetic,
+}
+
+impl Server {
+    fn send(&mut self, mut message: Value) -> Result<()> {
+        message["jsonrpc"] = json!("2.0");
+        write_message(&mut *self.output, &message)
+    }
+
+    fn publish(&mut self, uri: &str) -> Result<()> {
+        let text = self.docs.get(uri).cloned().unwrap_or_default();
+        let diagnostic = |line: usize, severity: i64, message: &str| {
+            let line = line.saturating_sub(1);
+            let len = utf16_len(text.lines().nth(line).unwrap_or_default());
+            json!({
+                "range": { "start": { "line": line, "character": 0 }, "end": { "line": line, "character": len } },
+                "severity": severity,
+                "source": "rolang",
+                "message": message,
+            })
+        };
+        let diagnostics: Vec<_> = match parse(&text, &self.dialect) {
+            (root, errors) if errors.is_empty() => infer_with(&root, self.arithmetic).errors.iter()
+                .map(|e| diagnostic(e.line, SEVERITY_ERROR, &e.message))
+                .chain(lint(&root).iter().map(|w| diagnostic(w.line, SEVERITY_WARNING, &format!("{} [{}]", w.message, w.lint.code()))))
+                .collect(),
+            (_, errors) => errors.iter().map(|e| diagnostic(e.line, SEVERITY_ERROR, &e.message)).collect(),
+        };
+        self.send(json!({
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }))
+    }
+
+    /// Numele de la poziția cerută și toate aparițiile lui.
+    fn name_at(&self, params: &Value) -> Option<(String, Vec<Name>)> {
+        let text = self.docs.get(params["textDocument"]["uri"].as_str()?)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        let names = names(text, &self.dialect);
+        let found = names.iter().find(|n| {
+            let start = position(text, n.line, n.col)["character"].as_u64().unwrap() as usize;
+            let end = position(text, n.line, n.col + n.len)["character"].as_u64().unwrap() as usize;
+            n.line == line && start <= character && character <= end
+        })?.name.clone();
+        if is_builtin(&found) {
+            return None;
+        }
+        let occurrences = names.into_iter().filter(|n| n.name == found).collect();
+        Some((found, occurrences))
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let control = self.dialect.breaks.iter().chain(&self.dialect.continues).map(|k| k.as_str());
+        let mut items: Vec<Value> = KEYWORDS.iter().copied().chain(control)
+            .map(|k| json!({ "label": k, "kind": COMPLETION_KEYWORD }))
+            .chain(BUILTINS.iter().map(|b| json!({ "label": b.name, "kind": COMPLETION_FUNCTION })))
+            .collect();
+        if let Some(text) = params["textDocument"]["uri"].as_str().and_then(|uri| self.docs.get(uri)) {
+            let vars: BTreeSet<_> = names(text, &self.dialect).into_iter().map(|n| n.name).filter(|n| !is_builtin(n)).collect();
+            items.extend(vars.iter().map(|v| json!({ "label": v, "kind": COMPLETION_VARIABLE })));
+        }
+        json!(items)
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let (name, _) = match self.name_at(params) {
+            Some(x) => x,
+            None => return Value::Null,
+        };
+        let text = &self.docs[params["textDocument"]["uri"].as_str().unwrap()];
+        let t = infer_with(&parse(text, &self.dialect).0, self.arithmetic).vars.get(&name).map(|t| t.to_string());
+        let t = t.unwrap_or_else(|| "tip necunoscut".to_string());
+        json!({ "contents": { "kind": "markdown", "value": format!("`{}`: {}", name, t) } })
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let uri = &params["textDocument"]["uri"];
+        match self.name_at(params) {
+            Some((_, occurrences)) => match occurrences.iter().find(|n| n.assigned) {
+                Some(def) => json!({ "uri": uri, "range": range(&self.docs[uri.as_str().unwrap()], def) }),
+                None => Value::Null,
+            },
+            None => Value::Null,
+        }
+    }
+
+    fn rename(&self, params: &Value) -> std::result::Result<Value, String> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let new_name = params["newName"].as_str().unwrap_or_default();
+        let mut lexer = Lexer::with_dialect(new_name.to_string(), self.dialect.clone());
+        match (lexer.next(), lexer.next()) {
+            (Ok(Token::Identifier(x)), Ok(Token::EOF)) if !is_builtin(&x) => (),
+            _ => return Err(format!("`{}` nu este un nume valid de variabilă.", new_name)),
+        }
+        let (_, occurrences) = self.name_at(params).ok_or("Aici nu este o variabilă.")?;
+        let text = &self.docs[uri];
+        let edits: Vec<_> = occurrences.iter().map(|n| json!({ "range": range(text, n), "newText": new_name })).collect();
+        Ok(json!({ "changes": { uri: edits } }))
+    }
+
+    fn formatting(&self, params: &Value) -> Value {
+        let text = match params["textDocument"]["uri"].as_str().and_then(|uri| self.docs.get(uri)) {
+            Some(t) => t,
+            None => return Value::Null,
+        };
+        match format_source(text, self.dialect.clone()) {
+            Ok(formatted) => json!([{
+                "range": { "start": { "line": 0, "character": 0 }, "end": end_of(text) },
+                "newText": formatted,
+            }]),
+            Err(_) => Value::Null,
+        }
+    }
+
+    /// Tratează o cerere; `Err` devine un răspuns de eroare.
+    fn request(&mut self, method: &str, params: &Value) -> std::result::Result<Value, (i64, String)> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "completionProvider": {},
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "renameProvider": true,
+                    "documentFormattingProvider": true,
+                },
+                "serverInfo": { "name": "rolang", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => Ok(Value::Null),
+            "textDocument/completion" => Ok(self.completion(params)),
+            "textDocument/hover" => Ok(self.hover(params)),
+            "textDocument/definition" => Ok(self.definition(params)),
+            "textDocument/rename" => self.rename(params).map_err(|e| (-32602, e)),
+            "textDocument/formatting" => Ok(self.formatting(params)),
+            _ => Err((-32601, format!("Metodă necunoscută: `{}`.", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Result<()> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        match method {
+            "textDocument/didOpen" => {
+                self.docs.insert(uri.clone(), params["textDocument"]["text"].as_str().unwrap_or_default().to_string());
+                self.publish(&uri)?;
+            }
+            "textDocument/didChange" => {
+                // Sincronizare completă: ultima modificare conține tot textul.
+                if let Some(change) = params["contentChanges"].as_array().and_then(|c| c.last()) {
+                    self.docs.insert(uri.clone(), change["text"].as_str().unwrap_or_default().to_string());
+                    self.publish(&uri)?;
+                }
+            }
+            "textDocument/didClose" => {
+                self.docs.remove(&uri);
+                self.send(json!({
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": uri, "diagnostics": [] },
+                }))?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+/// Serverul LSP: citește cereri din `input` și scrie răspunsurile în `output`
+/// până la notificarea `exit`. Sursele sunt analizate în `dialect`, iar tipurile
+/// sunt deduse cu `arithmetic`, ca în `rolang check`.
+pub fn serve(mut input: Box<dyn BufRead>, output: Box<dyn Write>, arithmetic: Arithmetic, dialect: Dialect) -> Result<()> {
+    let mut server = Server { output, docs: HashMap::new(), dialect, arithmetic };
+    while let Some(message) = read_message(&mut *input)? {
+        let method = message["method"].as_str().unwrap_or_default().to_string();
+        let params = &message["params"];
+        if method == "exit" {
+            break;
+        }
+        match message.get("id") {
+            Some(id) => {
+                let response = match server.request(&method, params) {
+                    Ok(result) => json!({ "id": id, "result": result }),
+                    Err((code, msg)) => json!({ "id": id, "error": { "code": code, "message": msg } }),
+                };
+                server.send(response)?;
+            }
+            None => server.notification(&method, params)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, Cursor};
+    use serde_json::{json, Value};
+
+    use super::{names, serve};
+    use crate::lexer::Dialect;
+    use crate::ops::{Arithmetic, Integers};
+    use crate::testing::Shared;
+    use crate::rpc::{read_message, write_message};
+
+    const URI: &str = "file:///p.ro";
+
+    /// Trimite mesajele și întoarce răspunsurile serverului, în ordine.
+    fn session(messages: Vec<Value>, arithmetic: Arithmetic, dialect: Dialect) -> Vec<Value> {
+        let mut input = Vec::new();
+        for m in messages {
+            write_message(&mut input, &m).unwrap();
+        }
+        let out = Shared::default();
+        serve(Box::new(Cursor::new(input)), Box::new(out.clone()), arithmetic, dialect).unwrap();
+        let bytes = out.0.borrow().clone();
+        let mut reader: Box<dyn BufRead> = Box::new(Cursor::new(bytes));
+        let mut replies = Vec::new();
+        while let Some(m) = read_message(&mut *reader).unwrap() {
+            replies.push(m);
+        }
+        replies
+    }
+
+    fn at(id: i64, method: &str, line: usize, character: usize) -> Value {
+        json!({ "id": id, "method": method, "params": {
+            "textDocument": { "uri": URI }, "position": { "line": line, "character": character }, "newName": "suma",
+        }})
+    }
+
+    #[test]
+    fn diagnostics_and_navigation() {
+        let source = "citește n (număr natural)\ns <- 0\npentru i <- 1, n execută\n    s <- s + i * 0.5\n■\nscrie s\n";
+        let replies = session(vec![
+            json!({ "id": 1, "method": "initialize", "params": {} }),
+            json!({ "method": "textDocument/didOpen", "params": { "textDocument": { "uri": URI, "text": "x <- (\n" } } }),
+            json!({ "method": "textDocument/didChange", "params": { "textDocument": { "uri": URI }, "contentChanges": [{ "text": source }] } }),
+            at(2, "textDocument/hover", 1, 0),
+            at(3, "textDocument/hover", 0, 9),
+            at(4, "textDocument/definition", 5, 7),
+            at(5, "textDocument/rename", 3, 4),
+            json!({ "id": 6, "method": "textDocument/formatting", "params": { "textDocument": { "uri": URI } } }),
+            json!({ "method": "exit" }),
+        ], Arithmetic::default(), Dialect::default());
+        assert_eq!(replies[0]["result"]["capabilities"]["renameProvider"], json!(true));
+        assert_eq!(replies[1]["params"]["diagnostics"][0]["range"]["start"]["line"], json!(0));
+        assert_eq!(replies[2]["params"]["diagnostics"], json!([]));
+        assert_eq!(replies[3]["result"]["contents"]["value"], json!("`s`: număr real"));
+        assert_eq!(replies[4]["result"]["contents"]["value"], json!("`n`: număr natural"));
+        assert_eq!(replies[5]["result"]["range"], json!({ "start": { "line": 1, "character": 0 }, "end": { "line": 1, "character": 1 } }));
+        let edits = replies[6]["result"]["changes"][URI].as_array().unwrap();
+        assert_eq!(edits.len(), 4);
+        assert_eq!(edits[3]["range"]["start"], json!({ "line": 5, "character": 6 }));
+        assert_eq!(replies[7]["result"][0]["newText"], json!("citește n (număr natural)\ns ← 0\npentru i ← 1, n execută\n    s ← s + i * 0.5\n■\nscrie s\n"));
+    }
+
+    #[test]
+    fn rename_rejects_keywords() {
+        let replies = session(vec![
+            json!({ "method": "textDocument/didOpen", "params": { "textDocument": { "uri": URI, "text": "a <- 1\n" } } }),
+            json!({ "id": 1, "method": "textDocument/rename", "params": {
+                "textDocument": { "uri": URI }, "position": { "line": 0, "character": 0 }, "newName": "dacă" } }),
+            json!({ "id": 2, "method": "textDocument/completion", "params": { "textDocument": { "uri": URI }, "position": { "line": 0, "character": 0 } } }),
+        ], Arithmetic::default(), Dialect::default());
+        assert_eq!(replies[1]["error"]["code"], json!(-32602));
+        assert!(replies[2]["result"].as_array().unwrap().contains(&json!({ "label": "a", "kind": 6 })));
+
+        let found: Vec<_> = names("citește din \"a.in\" n\nscrie în \"a.out\" n\n", &Dialect::default()).into_iter().map(|n| (n.name, n.assigned)).collect();
+        assert_eq!(found, vec![("n".to_string(), true), ("n".to_string(), false)]);
+
+        let found: Vec<_> = names("v[i], w[[i / 2]] <- 1, 2\nv[i] ↔ v[j]\n", &Dialect::default()).into_iter().filter(|n| n.assigned).map(|n| (n.name, n.line)).collect();
+        assert_eq!(found, vec![("v".to_string(), 0), ("w".to_string(), 0), ("v".to_string(), 1), ("v".to_string(), 1)]);
+    }
+
+    #[test]
+    fn bad_number() {
+        let replies = session(vec![
+            json!({ "method": "textDocument/didOpen", "params": { "textDocument": { "uri": URI, "text": "x <- 0\n" } } }),
+            json!({ "method": "textDocument/didChange", "params": { "textDocument": { "uri": URI }, "contentChanges": [{ "text": "x <- 01\n" }] } }),
+            at(1, "textDocument/hover", 0, 0),
+        ], Arithmetic::default(), Dialect::default());
+        assert_eq!(replies[1]["params"]["diagnostics"][0]["message"], json!("Invalid number literal"));
+        assert_eq!(replies[2]["id"], json!(1));
+    }
+
+    #[test]
+    fn arithmetic() {
+        let source = "citește n (număr natural)\na <- n / 2\nb <- 2.5 % 2\nscrie a, b\n";
+        let messages = || vec![
+            json!({ "method": "textDocument/didOpen", "params": { "textDocument": { "uri": URI, "text": source } } }),
+            at(1, "textDocument/hover", 1, 0),
+        ];
+        let replies = session(messages(), Arithmetic::default(), Dialect::default());
+        assert_eq!(replies[0]["params"]["diagnostics"], json!([]));
+        assert_eq!(replies[1]["result"]["contents"]["value"], json!("`a`: număr real"));
+
+        let replies = session(messages(), Arithmetic { integers: Integers::Cpp(32), exact: false }, Dialect::default());
+        assert_eq!(replies[0]["params"]["diagnostics"][0]["range"]["start"]["line"], json!(2));
+        assert_eq!(replies[1]["result"]["contents"]["value"], json!("`a`: număr natural"));
+    }
+
+    #[test]
+    fn dialect_keywords() {
+        let dialect = Dialect { breaks: vec!["break".into()], continues: vec!["continue".into()] };
+        let replies = session(vec![
+            json!({ "method": "textDocument/didOpen", "params": { "textDocument": { "uri": URI, "text": "a <- 1\ncât timp a execută\nbreak\n■\n" } } }),
+            json!({ "id": 1, "method": "textDocument/completion", "params": { "textDocument": { "uri": URI }, "position": { "line": 0, "character": 0 } } }),
+            json!({ "id": 2, "method": "textDocument/formatting", "params": { "textDocument": { "uri": URI } } }),
+        ], Arithmetic::default(), dialect);
+        assert_eq!(replies[0]["params"]["diagnostics"], json!([]));
+        let items = replies[1]["result"].as_array().unwrap();
+        assert!(items.contains(&json!({ "label": "break", "kind": 14 })));
+        assert!(!items.contains(&json!({ "label": "întrerupe", "kind": 14 })));
+        assert_eq!(replies[2]["result"][0]["newText"], json!("a ← 1\ncât timp a execută\n    break\n■\n"));
+    }
+}
@@ -1,44 +1,286 @@
 #![feature(let_chains)]
 
-use std::fs::read_to_string;
-use std::io::Write;
+use std::fs::{read_to_string, File};
+use std::io::{BufRead, BufReader, Write};
 use std::process::exit;
 use std::path::Path;
-use std::env;
+use std::time::Duration;
+use std::{env, rc::Rc, cell::RefCell};
 
 use repl::{repl, exec};
+use lexer::{Dialect, Lexer};
+use parser::Parser;
+use interpreter::{Environment, Limits};
+use trace::{trace, TraceFormat};
+use debug::debug;
+use dap::serve;
+use lint::{lint, Config, Level, Lint};
+use ops::{Arithmetic, Format, Fractions, Integers, MAX_DECIMALS};
 
 mod lexer;
 mod parser;
 mod interpreter;
 mod repl;
+mod trace;
+mod debug;
+mod rpc;
+mod dap;
+mod formatter;
+mod lsp;
+mod lint;
+mod types;
+mod builtins;
+mod ops;
+#[cfg(test)]
+mod testing;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+fn fail(msg: String) -> ! {
+    std::io::stderr().write_all(format!("{}\n", msg).as_bytes()).unwrap();
+    exit(1);
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: Option<&String>) -> T {
+    match value.map(|v| v.parse::<T>()) {
+        Some(Ok(x)) => x,
+        _ => fail(format!("Opțiunea `{}` așteaptă un număr.", flag)),
+    }
+}
+
+/// Afișează erorile de sintaxă și avertismentele; iese cu 1 dacă există
+/// erori sau avertismente interzise.
+fn check(filep: &str, input: &str, lints: &Config, arithmetic: Arithmetic, dialect: Dialect) -> ! {
+    let mut parser = Parser::new(Lexer::with_dialect(input.to_string(), dialect));
+    let root = parser.parse();
+    for err in &parser.errors {
+        println!("{}:{}: eroare: {}", filep, err.line, err.message);
+    }
+    let types = types::infer_with(&root, arithmetic);
+    for err in &types.errors {
+        println!("{}:{}: eroare [tip]: {}", filep, err.line, err.message);
+    }
+    if !parser.errors.is_empty() || !types.errors.is_empty() {
+        exit(1);
+    }
+
+    let mut denied = false;
+    for warning in lint(&root) {
+        let level = lints.level(warning.lint);
+        let kind = match level {
+            Level::Allow => continue,
+            Level::Warn => "avertisment",
+            Level::Deny => "eroare",
+        };
+        denied |= level == Level::Deny;
+        println!("{}:{}: {} [{}]: {}", filep, warning.line, kind, warning.lint.code(), warning.message);
+    }
+    exit(if denied { 1 } else { 0 });
+}
+
 fn main() {
     let args: Vec<_> = env::args().collect();
-    for i in &args {
-        if matches!(i.as_str(), "--version" | "-v" | "--versiune") {
-            println!("{} v{}", args[0], VERSION);
-            exit(0);
+    let mut limits = Limits::default();
+    let mut command: Option<String> = None;
+    let mut filep: Option<String> = None;
+    let mut trace_format = TraceFormat::Table;
+    let mut trace_vars: Option<Vec<String>> = None;
+    let mut lints = Config::default();
+    let mut lenient = false;
+    let mut sandbox = false;
+    let mut arithmetic = Arithmetic::default();
+    let mut format = Format::default();
+    let mut dialect = Dialect::default();
+    let mut big = false;
+    let mut cpp: Option<u32> = None;
+    let mut input_file: Option<String> = None;
+    let mut output_file: Option<String> = None;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--version" | "-v" | "--versiune" => {
+                println!("{} v{}", args[0], VERSION);
+                exit(0);
+            }
+            "--max-steps" | "--max-pași" => {
+                limits.max_steps = Some(parse_number(&args[i], args.get(i + 1)));
+                i += 1;
+            }
+            "--timeout" | "--timp" => {
+                limits.timeout = Some(Duration::from_millis(parse_number(&args[i], args.get(i + 1))));
+                i += 1;
+            }
+            "--max-depth" | "--max-adâncime" => {
+                limits.max_depth = Some(parse_number(&args[i], args.get(i + 1)));
+                i += 1;
+            }
+            "--format" => {
+                trace_format = match args.get(i + 1).map(|f| TraceFormat::parse(f)) {
+                    Some(Ok(f)) => f,
+                    Some(Err(e)) => fail(e.to_string()),
+                    None => fail("Opțiunea `--format` așteaptă un format.".to_string()),
+                };
+                i += 1;
+            }
+            "--vars" | "--variabile" => {
+                match args.get(i + 1) {
+                    Some(v) => trace_vars = Some(v.split(',').map(|x| x.trim().to_lowercase()).collect()),
+                    None => fail(format!("Opțiunea `{}` așteaptă o listă de variabile.", args[i])),
+                }
+                i += 1;
+            }
+            "--decimals" | "--zecimale" => {
+                let decimals = parse_number(&args[i], args.get(i + 1));
+                if decimals > MAX_DECIMALS {
+                    fail(format!("Opțiunea `{}` acceptă cel mult {} zecimale.", args[i], MAX_DECIMALS));
+                }
+                format.decimals = Some(decimals);
+                i += 1;
+            }
+            "--comma" | "--virgulă" => format.comma = true,
+            "--scientific" | "--științific" => format.scientific = true,
+            "--booleans" | "--logice" => {
+                let texts = args.get(i + 1).unwrap_or_else(|| fail(format!("Opțiunea `{}` așteaptă două texte, ca `DA,NU`.", args[i])));
+                format.booleans = match texts.split_once(',') {
+                    Some((t, f)) if !f.contains(',') => Some((t.to_string(), f.to_string())),
+                    _ => fail(format!("Opțiunea `{}` așteaptă două texte separate prin virgulă, nu `{}`.", args[i], texts)),
+                };
+                i += 1;
+            }
+            "--break" | "--întrerupe" | "--continue" | "--continuă" => {
+                let names = args.get(i + 1).unwrap_or_else(|| fail(format!("Opțiunea `{}` așteaptă o listă de nume.", args[i])));
+                let names = names.split(',').map(|x| x.trim().to_lowercase()).collect();
+                if matches!(args[i].as_str(), "--break" | "--întrerupe") {
+                    dialect.breaks = names;
+                } else {
+                    dialect.continues = names;
+                }
+                i += 1;
+            }
+            "--lenient" | "--tolerant" => lenient = true,
+            "--sandbox" | "--izolat" => sandbox = true,
+            "--bigint" | "--numere-mari" => big = true,
+            x if x == "--cpp" || x.starts_with("--cpp=") => {
+                cpp = match x.split_once('=').map(|(_, v)| v) {
+                    None | Some("32") => Some(32),
+                    Some("64") => Some(64),
+                    Some(v) => fail(format!("Opțiunea `--cpp` acceptă 32 sau 64 de biți, nu `{}`.", v)),
+                };
+            }
+            "--in" | "--intrare" | "--out" | "--ieșire" => {
+                let file = args.get(i + 1).cloned().unwrap_or_else(|| fail(format!("Opțiunea `{}` așteaptă un fișier.", args[i])));
+                if matches!(args[i].as_str(), "--in" | "--intrare") {
+                    input_file = Some(file);
+                } else {
+                    output_file = Some(file);
+                }
+                i += 1;
+            }
+            "--allow" | "--permite" | "--deny" | "--interzice" => {
+                let level = if matches!(args[i].as_str(), "--allow" | "--permite") { Level::Allow } else { Level::Deny };
+                let names = args.get(i + 1).unwrap_or_else(|| fail(format!("Opțiunea `{}` așteaptă o listă de avertismente.", args[i])));
+                for name in names.split(',') {
+                    match Lint::parse(name.trim()) {
+                        Ok(l) => lints.set(l, level),
+                        Err(e) => fail(e.to_string()),
+                    }
+                }
+                i += 1;
+            }
+            x if x == "--exact" || x.starts_with("--exact=") => {
+                arithmetic.exact = true;
+                format.fractions = match x.split_once('=').map(|(_, v)| v) {
+                    None | Some("fracție" | "fraction") => Fractions::Fraction,
+                    Some("zecimal" | "decimal") => Fractions::Decimal,
+                    Some(v) => fail(format!("Opțiunea `--exact` acceptă `fracție` sau `zecimal`, nu `{}`.", v)),
+                };
+            }
+            x if x.starts_with("--") => fail(format!("Opțiune necunoscută: `{}`.", x)),
+            x if command.is_none() && filep.is_none() && matches!(x, "trace" | "urmărește") => command = Some("trace".to_string()),
+            x if command.is_none() && filep.is_none() && matches!(x, "debug" | "depanează") => command = Some("debug".to_string()),
+            x if command.is_none() && filep.is_none() && matches!(x, "dap" | "lsp") => command = Some(x.to_string()),
+            x if command.is_none() && filep.is_none() && matches!(x, "check" | "verifică") => command = Some("check".to_string()),
+            x => filep = Some(x.to_string()),
+        }
+        i += 1;
+    }
+
+    arithmetic.integers = match (big, cpp) {
+        (true, Some(_)) => fail("Opțiunile `--bigint` și `--cpp` nu pot fi folosite împreună.".to_string()),
+        (_, Some(_)) if arithmetic.exact => fail("Opțiunile `--exact` și `--cpp` nu pot fi folosite împreună.".to_string()),
+        (true, None) => Integers::Big,
+        (false, Some(bits)) => Integers::Cpp(bits),
+        (false, None) => Integers::Checked,
+    };
+
+    if command.as_deref() == Some("dap") {
+        let stdin = std::io::stdin();
+        if let Err(e) = serve(Box::new(stdin.lock()), Box::new(std::io::stdout()), limits, arithmetic, format, dialect) {
+            fail(format!("Eroare DAP: {}", e));
+        }
+        return;
+    }
+
+    if command.as_deref() == Some("lsp") {
+        let stdin = std::io::stdin();
+        if let Err(e) = lsp::serve(Box::new(stdin.lock()), Box::new(std::io::stdout()), arithmetic, dialect) {
+            fail(format!("Eroare LSP: {}", e));
         }
+        return;
     }
-    if args.len() > 1 {
-        let mut filep = args[1].clone();
+
+    if let Some(mut filep) = filep {
         let mut file = Path::new(&filep);
         if !file.exists() {
             filep.push_str(".ro");
             file = Path::new(&filep);
             if !file.exists() {
-                std::io::stderr().write(format!("Nu există fișierul `{}`!\n", filep).as_bytes()).unwrap();
-                exit(1);
+                fail(format!("Nu există fișierul `{}`!", filep));
             }
         }
         let input = read_to_string(file).unwrap();
 
-        exec(&input).unwrap();
+        if command.as_deref() == Some("check") {
+            check(&filep, &input, &lints, arithmetic, dialect);
+        }
+
+        let envb = Rc::new(RefCell::new(Environment::new()));
+        envb.borrow_mut().limits = limits;
+        envb.borrow_mut().lenient = lenient;
+        envb.borrow_mut().sandbox = sandbox;
+        envb.borrow_mut().arithmetic = arithmetic;
+        envb.borrow_mut().format = format;
+        envb.borrow_mut().dialect = dialect;
+        if let Some(path) = input_file {
+            let mut reader = BufReader::new(File::open(&path).unwrap_or_else(|e| fail(format!("Nu pot deschide fișierul `{}`: {}.", path, e))));
+            envb.borrow_mut().input = Box::new(move |buf| reader.read_line(buf));
+        }
+        if let Some(path) = output_file {
+            let file = File::create(&path).unwrap_or_else(|e| fail(format!("Nu pot crea fișierul `{}`: {}.", path, e)));
+            envb.borrow_mut().output = Box::new(file);
+        }
+        let res = match command.as_deref() {
+            Some("trace") => {
+                let (table, res) = trace(&input, envb, trace_vars);
+                println!();
+                print!("{}", table.borrow().render(trace_format));
+                res
+            }
+            Some("debug") => {
+                debug(&input, envb, Box::new(|buf| std::io::stdin().read_line(buf)), Box::new(std::io::stdout()))
+            }
+            _ => exec(&input, envb),
+        };
+        if let Err(e) = res {
+            fail(format!("Eroare ROLang: {}", e));
+        }
         return;
     }
 
-    repl();
+    if command.is_some() {
+        fail("Lipsește fișierul programului.".to_string());
+    }
+
+    repl(limits, arithmetic, format, dialect);
 }
@@ -0,0 +1,767 @@
+use std::{cmp::Ordering, collections::HashMap};
+use anyhow::{Result, anyhow};
+use num_bigint::{BigInt, Sign};
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
+
+use crate::interpreter::{Object, truthy};
+use crate::parser::ASTNodeValue;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BinOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Mod,
+    Power,
+    Equal,
+    NotEqual,
+    LessThan,
+    GreaterThan,
+    LessThanEqual,
+    GreaterThanEqual,
+}
+
+impl BinOp {
+    pub fn from_node(value: &ASTNodeValue) -> Option<BinOp> {
+        Some(match value {
+            ASTNodeValue::Add => BinOp::Add,
+            ASTNodeValue::Subtract => BinOp::Subtract,
+            ASTNodeValue::Multiply => BinOp::Multiply,
+            ASTNodeValue::Divide => BinOp::Divide,
+            ASTNodeValue::Mod => BinOp::Mod,
+            ASTNodeValue::Power => BinOp::Power,
+            ASTNodeValue::Equal => BinOp::Equal,
+            ASTNodeValue::NotEqual => BinOp::NotEqual,
+            ASTNodeValue::LessThan => BinOp::LessThan,
+            ASTNodeValue::GreaterThan => BinOp::GreaterThan,
+            ASTNodeValue::LessThanEqual => BinOp::LessThanEqual,
+            ASTNodeValue::GreaterThanEqual => BinOp::GreaterThanEqual,
+            _ => return None,
+        })
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Subtract => "-",
+            BinOp::Multiply => "*",
+            BinOp::Divide => "/",
+            BinOp::Mod => "%",
+            BinOp::Power => "^",
+            BinOp::Equal => "=",
+            BinOp::NotEqual => "≠",
+            BinOp::LessThan => "<",
+            BinOp::GreaterThan => ">",
+            BinOp::LessThanEqual => "≤",
+            BinOp::GreaterThanEqual => "≥",
+        }
+    }
+}
+
+/// Cum se calculează cu numere întregi.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Integers {
+    /// Numere pe 64 de biți; un rezultat care nu încape este o eroare.
+    #[default]
+    Checked,
+    /// Numere întregi oricât de mari (`--bigint`).
+    Big,
+    /// Ca în C++ (`--cpp`): numere pe 32 sau 64 de biți care se reiau la
+    /// depășire, iar `/` între întregi dă câtul trunchiat.
+    Cpp(u32),
+}
+
+/// Regulile de calcul alese din linia de comandă.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Arithmetic {
+    pub integers: Integers,
+    /// `/` între numere întregi dă o fracție exactă (`--exact`).
+    pub exact: bool,
+}
+
+/// Cum afișează `scrie` o fracție.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Fractions {
+    /// `7/2`
+    #[default]
+    Fraction,
+    /// `3.5`, cu perioada între paranteze: `0.(3)`
+    Decimal,
+}
+
+/// Cele mai multe zecimale cu care se poate scrie un număr; `format!` nu
+/// acceptă precizii peste `u16::MAX`.
+pub const MAX_DECIMALS: usize = 1000;
+
+/// Cea mai mare lungime, în octeți, a unui șir obținut prin repetare;
+/// peste ea, `'ab' * n` este o eroare în loc să ceară prea multă memorie.
+pub const MAX_STRING: usize = 1 << 26;
+
+/// Cum scrie `scrie` numerele și valorile logice.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Format {
+    /// Zecimale fixe pentru numerele reale și fracții (`--zecimale`).
+    pub decimals: Option<usize>,
+    /// Virgula ca separator zecimal (`--virgulă`).
+    pub comma: bool,
+    /// Notația științifică pentru numerele reale (`--științific`).
+    pub scientific: bool,
+    pub fractions: Fractions,
+    /// Textele pentru `adevărat` și `fals` (`--logice`).
+    pub booleans: Option<(String, String)>,
+}
+
+impl Format {
+    /// O valoare scrisă după acest format; cu `cpp`, realii și valorile
+    /// logice fără format explicit se scriu ca de `cout`.
+    pub fn show(&self, value: &Object, cpp: bool) -> String {
+        let text = match value {
+            Object::Bool(x) => return match &self.booleans {
+                Some((t, f)) => if *x { t.clone() } else { f.clone() },
+                None if cpp => (*x as i64).to_string(),
+                None => value.to_string(),
+            },
+            Object::Float(x) => self.real(*x, cpp),
+            Object::Rational(_) if self.decimals.is_some() || self.scientific => self.real(real(value), cpp),
+            Object::Rational(x) if self.fractions == Fractions::Decimal => decimal(x),
+            _ => return value.to_string(),
+        };
+        if self.comma { text.replace('.', ",") } else { text }
+    }
+
+    fn real(&self, x: f64, cpp: bool) -> String {
+        match self.decimals.map(|d| d.min(MAX_DECIMALS)) {
+            _ if !x.is_finite() => cout(x),
+            _ if self.scientific => {
+                let s = match self.decimals {
+                    Some(d) => format!("{:.*e}", d.min(MAX_DECIMALS), x),
+                    None => format!("{:e}", x),
+                };
+                let (mantissa, exponent) = s.split_once('e').unwrap();
+                let exponent: i32 = exponent.parse().unwrap();
+                format!("{}e{}{:02}", mantissa, if exponent < 0 { '-' } else { '+' }, exponent.abs())
+            }
+            Some(d) => format!("{:.*}", d, x),
+            None if cpp => cout(x),
+            None => x.to_string(),
+        }
+    }
+}
+
+impl Arithmetic {
+    fn cpp(&self) -> Option<u32> {
+        match self.integers {
+            Integers::Cpp(bits) => Some(bits),
+            _ => None,
+        }
+    }
+
+    /// Un număr întreg scris în program sau citit, redus cu `--cpp` la
+    /// lățimea aleasă, ca la atribuirea într-un `int`.
+    pub fn narrow(&self, x: i64) -> i64 {
+        match self.cpp() {
+            Some(bits) => wrap(x, bits),
+            None => x,
+        }
+    }
+}
+
+/// Un număr întreg redus la `bits` biți, ca la atribuirea într-un `int`.
+fn wrap(x: i64, bits: u32) -> i64 {
+    if bits == 32 { x as i32 as i64 } else { x }
+}
+
+/// Un număr real scris ca de `cout` în C++: `%g`, cu 6 cifre semnificative.
+pub fn cout(x: f64) -> String {
+    if x.is_nan() {
+        return if x.is_sign_negative() { "-nan" } else { "nan" }.to_string();
+    }
+    if x.is_infinite() {
+        return if x > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    let trim = |s: String| if s.contains('.') { s.trim_end_matches('0').trim_end_matches('.').to_string() } else { s };
+    // Exponentul după rotunjirea la 6 cifre: 999999.5 se scrie 1e+06.
+    let scientific = format!("{:.5e}", x);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    if (-4..6).contains(&exponent) {
+        trim(format!("{:.*}", (5 - exponent) as usize, x))
+    } else {
+        format!("{}e{}{:02}", trim(mantissa.to_string()), if exponent < 0 { '-' } else { '+' }, exponent.abs())
+    }
+}
+
+/// Zecimalele scrise înainte de a renunța la căutarea perioadei.
+const MAX_DIGITS: usize = 100;
+
+/// O fracție scrisă zecimal, cu perioada între paranteze: `1/6` este
+/// `0.1(6)`.
+pub fn decimal(x: &BigRational) -> String {
+    let sign = if x.is_negative() { "-" } else { "" };
+    let (whole, mut rest) = (x.numer().abs() / x.denom(), x.numer().abs() % x.denom());
+    let mut digits = String::new();
+    let mut seen = HashMap::new();
+    while !rest.is_zero() {
+        if let Some(&start) = seen.get(&rest) {
+            digits.insert(start, '(');
+            digits.push(')');
+            break;
+        }
+        if digits.len() == MAX_DIGITS {
+            digits.push_str("...");
+            break;
+        }
+        seen.insert(rest.clone(), digits.len());
+        rest *= 10;
+        digits.push_str(&(&rest / x.denom()).to_string());
+        rest %= x.denom();
+    }
+    if digits.is_empty() {
+        return format!("{}{}", sign, whole);
+    }
+    format!("{}{}.{}", sign, whole, digits)
+}
+
+/// Un număr întreg mare, păstrat ca `Object::Int` dacă încape în 64 de biți.
+pub fn int(x: BigInt) -> Object {
+    match i64::try_from(&x) {
+        Ok(x) => Object::Int(x),
+        Err(_) => Object::BigInt(x),
+    }
+}
+
+/// Un rezultat exact: număr întreg dacă numitorul este 1, altfel fracție.
+fn exact(mode: Arithmetic, op: BinOp, left: &Object, right: &Object, x: BigRational) -> Result<Object> {
+    if !x.is_integer() {
+        return Ok(Object::Rational(x));
+    }
+    match int(x.to_integer()) {
+        Object::BigInt(_) if mode.integers != Integers::Big => Err(too_large(op, left, right)),
+        x => Ok(x),
+    }
+}
+
+fn ratio(value: &Object) -> BigRational {
+    match value {
+        Object::Rational(x) => x.clone(),
+        _ => BigRational::from_integer(big(value)),
+    }
+}
+
+fn big(value: &Object) -> BigInt {
+    match value {
+        Object::Int(x) => BigInt::from(*x),
+        Object::BigInt(x) => x.clone(),
+        _ => unreachable!(),
+    }
+}
+
+/// Felul unei valori, pentru mesajele de eroare.
+pub fn kind(value: &Object) -> &'static str {
+    match value {
+        Object::Int(_) | Object::BigInt(_) => "un număr întreg",
+        Object::Rational(_) => "o fracție",
+        Object::Float(_) => "un număr real",
+        Object::Bool(_) => "un boolean",
+        Object::String(_) => "un șir de caractere",
+        Object::Null => "`nul`",
+        Object::Cast(..) => "o adnotare",
+        Object::Array(_) => "un tablou",
+    }
+}
+
+fn meaningless(op: BinOp, left: &Object, right: &Object) -> anyhow::Error {
+    anyhow!("Operația `{}` nu are sens între {} și {}.", op.symbol(), kind(left), kind(right))
+}
+
+fn is_integer(value: &Object) -> bool {
+    matches!(value, Object::Int(_) | Object::BigInt(_))
+}
+
+/// Un număr întreg sau o fracție, fără erori de rotunjire.
+fn is_exact(value: &Object) -> bool {
+    matches!(value, Object::Int(_) | Object::BigInt(_) | Object::Rational(_))
+}
+
+/// Un număr de orice fel: întreg, fracție sau real.
+pub fn is_number(value: &Object) -> bool {
+    is_exact(value) || matches!(value, Object::Float(_))
+}
+
+/// Ordinea a două valori: numerele se compară după valoare, oricare ar fi
+/// tipul lor, iar șirurile alfabetic. Restul nu au o ordine.
+pub fn compare(left: &Object, right: &Object) -> Option<Ordering> {
+    match (left, right) {
+        (Object::Int(a), Object::Int(b)) => Some(a.cmp(b)),
+        _ if is_integer(left) && is_integer(right) => Some(big(left).cmp(&big(right))),
+        _ if is_exact(left) && is_exact(right) => Some(ratio(left).cmp(&ratio(right))),
+        _ if is_number(left) && is_number(right) => real(left).partial_cmp(&real(right)),
+        (Object::String(a), Object::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn real(value: &Object) -> f64 {
+    match value {
+        Object::Int(x) => *x as f64,
+        Object::Float(x) => *x,
+        // Prea mare pentru `f64` devine infinit, prins de `checked_real`.
+        Object::BigInt(x) => x.to_f64().unwrap_or(f64::INFINITY),
+        Object::Rational(x) => x.to_f64().unwrap_or(f64::INFINITY),
+        _ => unreachable!(),
+    }
+}
+
+/// Egalitatea: numerele sunt egale dacă au aceeași valoare (`1 = 1.0`),
+/// celelalte valori doar dacă sunt de același fel și identice. Valori de
+/// feluri diferite nu sunt egale.
+pub fn equal(left: &Object, right: &Object) -> bool {
+    if is_number(left) && is_number(right) {
+        return compare(left, right) == Some(Ordering::Equal);
+    }
+    left == right
+}
+
+fn too_large(op: BinOp, left: &Object, right: &Object) -> anyhow::Error {
+    anyhow!("Rezultatul lui {} {} {} este prea mare pentru un număr întreg.", left, op.symbol(), right)
+}
+
+/// Un rezultat real trebuie să fie un număr finit: `inf` și `NaN` devin erori.
+fn checked_real(mode: Arithmetic, op: BinOp, left: &Object, right: &Object, result: f64) -> Result<Object> {
+    if mode.cpp().is_some() {
+        return Ok(Object::Float(result));
+    }
+    if result.is_nan() {
+        Err(anyhow!("Rezultatul lui {} {} {} nu este un număr real.", left, op.symbol(), right))
+    } else if result.is_infinite() {
+        Err(anyhow!("Rezultatul lui {} {} {} este prea mare pentru un număr real.", left, op.symbol(), right))
+    } else {
+        Ok(Object::Float(result))
+    }
+}
+
+/// `+ - * %` între numere întregi: pe 64 de biți dacă rezultatul încape,
+/// altfel, cu `--bigint`, ca număr mare. Cu `--cpp`, și `/`.
+fn integer(mode: Arithmetic, op: BinOp, left: &Object, right: &Object) -> Result<Object> {
+    if let (Object::Int(a), Object::Int(b)) = (left, right) {
+        if let Some(bits) = mode.cpp() {
+            return Ok(Object::Int(wrap(match op {
+                BinOp::Add => a.wrapping_add(*b),
+                BinOp::Subtract => a.wrapping_sub(*b),
+                BinOp::Multiply => a.wrapping_mul(*b),
+                BinOp::Divide => a.wrapping_div(*b),
+                _ => a.wrapping_rem(*b),
+            }, bits)));
+        }
+        let result = match op {
+            BinOp::Add => a.checked_add(*b),
+            BinOp::Subtract => a.checked_sub(*b),
+            BinOp::Multiply => a.checked_mul(*b),
+            _ => a.checked_rem(*b),
+        };
+        match result {
+            Some(x) => return Ok(Object::Int(x)),
+            None if mode.integers != Integers::Big => return Err(too_large(op, left, right)),
+            None => (),
+        }
+    }
+    let (a, b) = (big(left), big(right));
+    Ok(int(match op {
+        BinOp::Add => a + b,
+        BinOp::Subtract => a - b,
+        BinOp::Multiply => a * b,
+        _ => a % b,
+    }))
+}
+
+/// `a ^ b`: întreg dacă ambele sunt întregi și exponentul nu e negativ,
+/// real în rest. Cu `--exact`, o fracție sau un exponent întreg negativ dau
+/// o fracție. Cu `--cpp`, ca `pow` din C++: mereu real.
+pub fn power(mode: Arithmetic, base: &Object, exponent: &Object) -> Result<Object> {
+    match exponent {
+        _ if mode.cpp().is_some() && is_number(base) && is_number(exponent) => {
+            Ok(Object::Float(real(base).powf(real(exponent))))
+        }
+        Object::Int(b) if *b >= 0 && is_integer(base) => {
+            let b = u32::try_from(*b).map_err(|_| too_large(BinOp::Power, base, exponent))?;
+            if let Object::Int(a) = base && let Some(x) = a.checked_pow(b) {
+                return Ok(Object::Int(x));
+            }
+            if mode.integers != Integers::Big {
+                return Err(too_large(BinOp::Power, base, exponent));
+            }
+            Ok(int(big(base).pow(b)))
+        }
+        _ if !is_number(base) || !is_number(exponent) => Err(meaningless(BinOp::Power, base, exponent)),
+        _ if real(base) == 0.0 && real(exponent) < 0.0 => Err(anyhow!("Împărțire la zero: {} ^ {}.", base, exponent)),
+        Object::Int(b) if is_exact(base) && (mode.exact || matches!(base, Object::Rational(_))) => {
+            let b = i32::try_from(*b).map_err(|_| too_large(BinOp::Power, base, exponent))?;
+            exact(mode, BinOp::Power, base, exponent, ratio(base).pow(b))
+        }
+        _ => checked_real(mode, BinOp::Power, base, exponent, real(base).powf(real(exponent))),
+    }
+}
+
+/// Aplică un operator binar. Regulile, pentru orice pereche de valori:
+///
+/// | operator       | operanzi                    | rezultat                           |
+/// |----------------|-----------------------------|------------------------------------|
+/// | `+`            | cel puțin un șir            | șir: textele lipite                |
+/// | `+ - * %`      | întreg și întreg            | întreg                             |
+/// | `+ - * / %`    | numere, cel puțin un real   | real                               |
+/// | `/`            | întreg și întreg            | real (`[a / b]` dă partea întreagă) |
+/// | `+ - * / %`    | întregi și fracții, `--exact` | fracție, sau întreg dacă se simplifică |
+/// | `*`            | șir și întreg `n ≥ 0`       | șirul repetat de `n` ori           |
+/// | `^`            | vezi `power`                |                                    |
+/// | `= ≠`          | orice                       | boolean, vezi `equal`              |
+/// | `< > ≤ ≥`      | numere sau două șiruri      | boolean, vezi `compare`            |
+///
+/// Orice altă pereche (booleeni sau `nul` în calcule, șiruri comparate cu
+/// numere, ...) este o eroare, la fel ca împărțirea la zero, un întreg care
+/// nu încape în 64 de biți (fără `--bigint`), un rezultat real infinit sau
+/// `NaN` și un șir repetat mai lung de `MAX_STRING` octeți.
+pub fn binary(mode: Arithmetic, op: BinOp, left: &Object, right: &Object) -> Result<Object> {
+    let ordering = |accept: fn(Ordering) -> bool| match compare(left, right) {
+        Some(o) => Ok(Object::Bool(accept(o))),
+        None => Err(meaningless(op, left, right)),
+    };
+    match op {
+        BinOp::Equal => return Ok(Object::Bool(equal(left, right))),
+        BinOp::NotEqual => return Ok(Object::Bool(!equal(left, right))),
+        BinOp::LessThan => return ordering(|o| o == Ordering::Less),
+        BinOp::GreaterThan => return ordering(|o| o == Ordering::Greater),
+        BinOp::LessThanEqual => return ordering(|o| o != Ordering::Greater),
+        BinOp::GreaterThanEqual => return ordering(|o| o != Ordering::Less),
+        BinOp::Power => return power(mode, left, right),
+        _ => (),
+    }
+    match (left, right) {
+        (Object::String(_), _) | (_, Object::String(_)) if op == BinOp::Add => {
+            Ok(Object::String(format!("{}{}", left, right)))
+        }
+        (Object::String(s), Object::Int(n)) | (Object::Int(n), Object::String(s)) if op == BinOp::Multiply => {
+            match usize::try_from(*n) {
+                Ok(n) if s.len().checked_mul(n).is_some_and(|len| len <= MAX_STRING) => Ok(Object::String(s.repeat(n))),
+                Ok(_) => Err(anyhow!("Șirul repetat de {} ori ar avea peste {} de octeți.", n, MAX_STRING)),
+                Err(_) => Err(anyhow!("Un șir nu poate fi repetat de {} ori.", n)),
+            }
+        }
+        _ if !is_number(left) || !is_number(right) => Err(meaningless(op, left, right)),
+        _ if op == BinOp::Mod && mode.cpp().is_some() && !(is_integer(left) && is_integer(right)) => {
+            Err(anyhow!("Cu `--cpp`, `%` se aplică doar numerelor întregi, ca în C++, nu lui {} % {}.", left, right))
+        }
+        _ if matches!(op, BinOp::Divide | BinOp::Mod) && real(right) == 0.0
+            && (mode.cpp().is_none() || is_integer(left) && is_integer(right)) => {
+            Err(anyhow!("Împărțire la zero: {} {} {}.", left, op.symbol(), right))
+        }
+        _ if is_integer(left) && is_integer(right) && (op != BinOp::Divide || mode.cpp().is_some()) => {
+            integer(mode, op, left, right)
+        }
+        _ if mode.exact && is_exact(left) && is_exact(right) => {
+            let (a, b) = (ratio(left), ratio(right));
+            exact(mode, op, left, right, match op {
+                BinOp::Add => a + b,
+                BinOp::Subtract => a - b,
+                BinOp::Multiply => a * b,
+                BinOp::Divide => a / b,
+                _ => a % b,
+            })
+        }
+        _ => {
+            let (a, b) = (real(left), real(right));
+            checked_real(mode, op, left, right, match op {
+                BinOp::Add => a + b,
+                BinOp::Subtract => a - b,
+                BinOp::Multiply => a * b,
+                BinOp::Divide => a / b,
+                _ => a % b,
+            })
+        }
+    }
+}
+
+/// `-x`, doar pentru numere.
+pub fn negate(mode: Arithmetic, value: &Object) -> Result<Object> {
+    match value {
+        Object::Int(x) => match x.checked_neg() {
+            _ if mode.cpp().is_some() => Ok(Object::Int(wrap(x.wrapping_neg(), mode.cpp().unwrap()))),
+            Some(x) => Ok(Object::Int(x)),
+            None if mode.integers == Integers::Big => Ok(int(-big(value))),
+            None => Err(anyhow!("Rezultatul lui -({}) este prea mare pentru un număr întreg.", x)),
+        },
+        Object::BigInt(x) => Ok(int(-x)),
+        Object::Rational(x) => Ok(Object::Rational(-x)),
+        Object::Float(x) => Ok(Object::Float(-x)),
+        _ => Err(anyhow!("Operația `-` nu are sens pentru {}.", kind(value))),
+    }
+}
+
+/// `[x]`, partea întreagă, doar pentru numere.
+pub fn floor(mode: Arithmetic, value: &Object) -> Result<Object> {
+    match value {
+        Object::Int(_) | Object::BigInt(_) => Ok(value.clone()),
+        Object::Float(x) if mode.cpp().is_some() => Ok(Object::Int(wrap(x.floor() as i64, mode.cpp().unwrap()))),
+        Object::Rational(x) => match int(x.floor().to_integer()) {
+            Object::BigInt(_) if mode.integers != Integers::Big => {
+                Err(anyhow!("Partea întreagă a lui {} este prea mare pentru un număr întreg.", x))
+            }
+            x => Ok(x),
+        },
+        // `i64::MAX as f64` este 2⁶³, deja în afara domeniului.
+        Object::Float(x) if x.floor() >= i64::MIN as f64 && x.floor() < i64::MAX as f64 => Ok(Object::Int(x.floor() as i64)),
+        Object::Float(x) if mode.integers == Integers::Big => Ok(int(BigInt::from_f64(x.floor()).unwrap())),
+        Object::Float(x) => Err(anyhow!("Partea întreagă a lui {} este prea mare pentru un număr întreg.", x)),
+        _ => Err(anyhow!("Partea întreagă nu are sens pentru {}.", kind(value))),
+    }
+}
+
+/// `[a / b]`: pentru numere întregi, câtul exact rotunjit în jos, fără să
+/// treacă prin numere reale; altfel ca `floor(a / b)`.
+pub fn floor_divide(mode: Arithmetic, left: &Object, right: &Object) -> Result<Object> {
+    if mode.cpp().is_some() || !is_integer(left) || !is_integer(right) || real(right) == 0.0 {
+        return floor(mode, &binary(mode, BinOp::Divide, left, right)?);
+    }
+    if let (Object::Int(a), Object::Int(b)) = (left, right) {
+        match a.checked_div(*b) {
+            Some(q) if a % b != 0 && (*a < 0) != (*b < 0) => return Ok(Object::Int(q - 1)),
+            Some(q) => return Ok(Object::Int(q)),
+            None if mode.integers != Integers::Big => return Err(too_large(BinOp::Divide, left, right)),
+            None => (),
+        }
+    }
+    let (a, b) = (big(left), big(right));
+    let q = &a / &b;
+    let negative = (a.sign() == Sign::Minus) != (b.sign() == Sign::Minus);
+    Ok(int(if negative && &q * &b != a { q - 1 } else { q }))
+}
+
+/// `not x` este opusul valorii de adevăr a lui `x`.
+pub fn not(value: &Object) -> Object {
+    Object::Bool(!truthy(value.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Arithmetic, BinOp, Format, Fractions, Integers, MAX_DECIMALS, MAX_STRING, binary, compare, cout, decimal, equal, floor, floor_divide, negate, not, power};
+
+    const CHECKED: Arithmetic = Arithmetic { integers: Integers::Checked, exact: false };
+    const BIG: Arithmetic = Arithmetic { integers: Integers::Big, exact: false };
+    const EXACT: Arithmetic = Arithmetic { integers: Integers::Checked, exact: true };
+    const CPP32: Arithmetic = Arithmetic { integers: Integers::Cpp(32), exact: false };
+    const CPP64: Arithmetic = Arithmetic { integers: Integers::Cpp(64), exact: false };
+    use crate::interpreter::Object;
+
+    fn show(res: anyhow::Result<Object>) -> String {
+        match res {
+            Ok(Object::Float(x)) => format!("{:?}", x),
+            Ok(Object::String(x)) => format!("{:?}", x),
+            Ok(x) => x.to_string(),
+            Err(_) => "!".to_string(),
+        }
+    }
+
+    fn error(res: anyhow::Result<Object>) -> String {
+        match res {
+            Ok(x) => panic!("nicio eroare: {}", x),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn matrix() {
+        let values = [Object::Int(4), Object::Float(2.0), Object::Bool(true), Object::String("ab".to_string()), Object::Null];
+        // Rânduri: operandul din stânga; coloane: cel din dreapta, în ordinea
+        // din `values`. `!` înseamnă eroare.
+        let table = [
+            (BinOp::Add, "8 6.0 ! \"4ab\" !  6.0 4.0 ! \"2ab\" !  ! ! ! \"adevăratab\" !
+                          \"ab4\" \"ab2\" \"abadevărat\" \"abab\" \"abnul\"  ! ! ! \"nulab\" !"),
+            (BinOp::Subtract, "0 2.0 ! ! !  -2.0 0.0 ! ! !  ! ! ! ! !  ! ! ! ! !  ! ! ! ! !"),
+            (BinOp::Multiply, "16 8.0 ! \"abababab\" !  8.0 4.0 ! ! !  ! ! ! ! !  \"abababab\" ! ! ! !  ! ! ! ! !"),
+            (BinOp::Divide, "1.0 2.0 ! ! !  0.5 1.0 ! ! !  ! ! ! ! !  ! ! ! ! !  ! ! ! ! !"),
+            (BinOp::Mod, "0 0.0 ! ! !  2.0 0.0 ! ! !  ! ! ! ! !  ! ! ! ! !  ! ! ! ! !"),
+            (BinOp::Power, "256 16.0 ! ! !  16.0 4.0 ! ! !  ! ! ! ! !  ! ! ! ! !  ! ! ! ! !"),
+            (BinOp::Equal, "adevărat fals fals fals fals  fals adevărat fals fals fals  fals fals adevărat fals fals
+                            fals fals fals adevărat fals  fals fals fals fals adevărat"),
+            (BinOp::NotEqual, "fals adevărat adevărat adevărat adevărat  adevărat fals adevărat adevărat adevărat
+                               adevărat adevărat fals adevărat adevărat  adevărat adevărat adevărat fals adevărat
+                               adevărat adevărat adevărat adevărat fals"),
+            (BinOp::LessThan, "fals fals ! ! !  adevărat fals ! ! !  ! ! ! ! !  ! ! ! fals !  ! ! ! ! !"),
+            (BinOp::GreaterThan, "fals adevărat ! ! !  fals fals ! ! !  ! ! ! ! !  ! ! ! fals !  ! ! ! ! !"),
+            (BinOp::LessThanEqual, "adevărat fals ! ! !  adevărat adevărat ! ! !  ! ! ! ! !  ! ! ! adevărat !  ! ! ! ! !"),
+            (BinOp::GreaterThanEqual, "adevărat adevărat ! ! !  fals adevărat ! ! !  ! ! ! ! !  ! ! ! adevărat !  ! ! ! ! !"),
+        ];
+        for (op, expected) in table {
+            let expected: Vec<_> = expected.split_whitespace().collect();
+            assert_eq!(expected.len(), 25, "{:?}", op);
+            for (i, left) in values.iter().enumerate() {
+                for (j, right) in values.iter().enumerate() {
+                    let got = show(binary(CHECKED, op, left, right));
+                    assert_eq!(got, expected[i * 5 + j], "{} {} {}", left, op.symbol(), right);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn numbers_and_errors() {
+        assert!(equal(&Object::Int(1), &Object::Float(1.0)));
+        assert!(!equal(&Object::Int(1), &Object::String("1".to_string())));
+        assert_eq!(show(binary(CHECKED, BinOp::Mod, &Object::Int(-7), &Object::Int(3))), "-1");
+        assert_eq!(show(binary(CHECKED, BinOp::Divide, &Object::Int(7), &Object::Int(2))), "3.5");
+        assert_eq!(show(binary(CHECKED, BinOp::Power, &Object::Int(2), &Object::Int(-2))), "0.25");
+        assert_eq!(error(binary(CHECKED, BinOp::Subtract, &Object::Int(1), &Object::String("a".to_string()))),
+                   "Operația `-` nu are sens între un număr întreg și un șir de caractere.");
+        assert_eq!(error(binary(CHECKED, BinOp::Mod, &Object::Int(1), &Object::Int(0))), "Împărțire la zero: 1 % 0.");
+        assert!(binary(CHECKED, BinOp::Multiply, &Object::String("a".to_string()), &Object::Int(-1)).is_err());
+        assert_eq!(error(binary(CHECKED, BinOp::Multiply, &Object::String("ab".to_string()), &Object::Int(100000000000))),
+                   format!("Șirul repetat de 100000000000 ori ar avea peste {} de octeți.", MAX_STRING));
+        let longest = binary(CHECKED, BinOp::Multiply, &Object::Int(MAX_STRING as i64 / 2), &Object::String("ab".to_string()));
+        assert!(matches!(longest, Ok(Object::String(s)) if s.len() == MAX_STRING));
+
+        let values = [Object::Int(4), Object::Float(-2.5), Object::Bool(true), Object::String("ab".to_string()), Object::Null];
+        let unary: Vec<_> = values.iter().map(|v| (show(negate(CHECKED, v)), show(floor(CHECKED, v)), not(v).to_string())).collect();
+        let expected = [("-4", "4", "fals"), ("2.5", "-3", "fals"), ("!", "!", "fals"), ("!", "!", "fals"), ("!", "!", "adevărat")];
+        for (got, expected) in unary.iter().zip(expected) {
+            assert_eq!((got.0.as_str(), got.1.as_str(), got.2.as_str()), expected);
+        }
+    }
+
+    #[test]
+    fn checked() {
+        let (int, real) = (Object::Int, Object::Float);
+        let cases = [
+            (BinOp::Divide, int(5), int(0), "Împărțire la zero: 5 / 0."),
+            (BinOp::Mod, real(2.5), int(0), "Împărțire la zero: 2.5 % 0."),
+            (BinOp::Divide, real(1.0), real(0.0), "Împărțire la zero: 1 / 0."),
+            (BinOp::Add, int(i64::MAX), int(1), "Rezultatul lui 9223372036854775807 + 1 este prea mare pentru un număr întreg."),
+            (BinOp::Subtract, int(i64::MIN), int(1), "Rezultatul lui -9223372036854775808 - 1 este prea mare pentru un număr întreg."),
+            (BinOp::Multiply, int(1 << 32), int(1 << 32), "Rezultatul lui 4294967296 * 4294967296 este prea mare pentru un număr întreg."),
+            (BinOp::Mod, int(i64::MIN), int(-1), "Rezultatul lui -9223372036854775808 % -1 este prea mare pentru un număr întreg."),
+            (BinOp::Power, int(10), int(19), "Rezultatul lui 10 ^ 19 este prea mare pentru un număr întreg."),
+            (BinOp::Power, int(0), int(-1), "Împărțire la zero: 0 ^ -1."),
+            (BinOp::Power, int(-8), real(0.5), "Rezultatul lui -8 ^ 0.5 nu este un număr real."),
+        ];
+        for (op, left, right, expected) in cases {
+            assert_eq!(error(binary(CHECKED, op, &left, &right)), expected);
+        }
+        assert!(error(binary(CHECKED, BinOp::Multiply, &real(1e300), &real(1e300))).ends_with("este prea mare pentru un număr real."));
+        assert!(negate(CHECKED, &int(i64::MIN)).is_err());
+        assert!(floor(CHECKED, &real(1e19)).is_err());
+        assert_eq!(show(floor(CHECKED, &real(-1e18))), "-1000000000000000000");
+    }
+
+    #[test]
+    fn big() {
+        let max = Object::Int(i64::MAX);
+        let above = binary(BIG, BinOp::Add, &max, &Object::Int(1)).unwrap();
+        assert_eq!(above.to_string(), "9223372036854775808");
+        assert!(matches!(above, Object::BigInt(_)));
+        // Rezultatele care încap din nou în 64 de biți redevin `Int`.
+        assert!(binary(BIG, BinOp::Subtract, &above, &Object::Int(1)).unwrap() == max);
+        assert_eq!(show(power(BIG, &Object::Int(2), &Object::Int(100))), "1267650600228229401496703205376");
+        assert_eq!(show(binary(BIG, BinOp::Mod, &above, &Object::Int(10))), "8");
+        assert_eq!(show(negate(BIG, &Object::Int(i64::MIN))), "9223372036854775808");
+        assert_eq!(show(floor(BIG, &Object::Float(1e19))), "10000000000000000000");
+        assert_eq!(show(floor_divide(BIG, &above, &Object::Int(-3))), "-3074457345618258603");
+        assert_eq!(show(floor_divide(CHECKED, &Object::Int(-7), &Object::Int(2))), "-4");
+        assert_eq!(show(binary(BIG, BinOp::Divide, &above, &Object::Int(2))), "4.611686018427388e18");
+        assert_eq!(compare(&above, &max), Some(std::cmp::Ordering::Greater));
+        assert!(!equal(&above, &Object::Float(1.0)) && equal(&above, &Object::Float(9223372036854775808.0)));
+        assert!(error(binary(CHECKED, BinOp::Add, &max, &Object::Int(1))).contains("prea mare"));
+    }
+
+    #[test]
+    fn exact() {
+        let (int, real) = (Object::Int, Object::Float);
+        let div = |a: i64, b: i64| binary(EXACT, BinOp::Divide, &int(a), &int(b)).unwrap();
+        let half = div(7, 2);
+        assert_eq!(half.to_string(), "7/2");
+        assert!(binary(EXACT, BinOp::Multiply, &half, &int(2)).unwrap() == int(7));
+        assert_eq!(show(binary(EXACT, BinOp::Add, &div(1, 3), &div(1, 6))), "1/2");
+        assert_eq!(show(binary(EXACT, BinOp::Mod, &half, &int(2))), "3/2");
+        assert_eq!(show(binary(EXACT, BinOp::Add, &half, &real(0.25))), "3.75");
+        assert_eq!(show(power(EXACT, &int(2), &int(-3))), "1/8");
+        assert_eq!(show(power(EXACT, &div(2, 3), &int(2))), "4/9");
+        assert_eq!(show(power(CHECKED, &int(2), &int(-3))), "0.125");
+        assert_eq!(show(floor(EXACT, &div(-7, 2))), "-4");
+        assert_eq!(show(floor_divide(EXACT, &half, &div(1, 3))), "10");
+        assert_eq!(show(negate(EXACT, &half)), "-7/2");
+        assert_eq!(compare(&div(1, 3), &real(0.34)), Some(std::cmp::Ordering::Less));
+        assert!(equal(&div(1, 2), &real(0.5)) && !equal(&div(1, 3), &int(0)));
+        assert_eq!(error(binary(EXACT, BinOp::Divide, &half, &int(0))), "Împărțire la zero: 7/2 / 0.");
+        assert_eq!(error(binary(EXACT, BinOp::Multiply, &div(i64::MAX, 2), &int(4))),
+                   "Rezultatul lui 9223372036854775807/2 * 4 este prea mare pentru un număr întreg.");
+
+        let decimals = [((1, 6), "0.1(6)"), ((-7, 2), "-3.5"), ((1, 7), "0.(142857)"), ((22, 1), "22"), ((-1, 3), "-0.(3)")];
+        for ((a, b), expected) in decimals {
+            assert_eq!(decimal(&num_rational::BigRational::new(a.into(), b.into())), expected);
+        }
+    }
+
+    #[test]
+    fn cpp() {
+        let (int, real) = (Object::Int, Object::Float);
+        let cases = [
+            (CPP32, BinOp::Divide, int(7), int(-2), "-3"),
+            (CPP32, BinOp::Mod, int(-7), int(2), "-1"),
+            (CPP32, BinOp::Mod, int(7), int(-2), "1"),
+            (CPP32, BinOp::Add, int(i32::MAX as i64), int(1), "-2147483648"),
+            (CPP32, BinOp::Multiply, int(65536), int(65536), "0"),
+            (CPP32, BinOp::Power, int(3), int(21), "10460353203.0"),
+            (CPP32, BinOp::Power, int(2), int(40), "1099511627776.0"),
+            (CPP64, BinOp::Power, int(2), int(-1), "0.5"),
+            (CPP32, BinOp::Power, int(0), int(-1), "inf"),
+            (CPP64, BinOp::Add, int(i32::MAX as i64), int(1), "2147483648"),
+            (CPP64, BinOp::Multiply, int(i64::MAX), int(2), "-2"),
+            (CPP64, BinOp::Divide, int(i64::MIN), int(-1), "-9223372036854775808"),
+            (CPP32, BinOp::Divide, int(7), real(2.0), "3.5"),
+            (CPP32, BinOp::Divide, real(1.0), int(0), "inf"),
+        ];
+        for (mode, op, left, right, expected) in cases {
+            assert_eq!(show(binary(mode, op, &left, &right)), expected, "{} {} {}", left, op.symbol(), right);
+        }
+        assert_eq!(error(binary(CPP32, BinOp::Divide, &int(1), &int(0))), "Împărțire la zero: 1 / 0.");
+        assert_eq!(error(binary(CPP32, BinOp::Mod, &real(5.5), &int(2))), "Cu `--cpp`, `%` se aplică doar numerelor întregi, ca în C++, nu lui 5.5 % 2.");
+        assert_eq!(error(binary(CPP64, BinOp::Mod, &int(5), &real(2.0))), "Cu `--cpp`, `%` se aplică doar numerelor întregi, ca în C++, nu lui 5 % 2.");
+        assert_eq!((CPP32.narrow(3000000000), CPP64.narrow(3000000000), CHECKED.narrow(3000000000)), (-1294967296, 3000000000, 3000000000));
+        assert_eq!(show(floor_divide(CPP32, &int(-7), &int(2))), "-3");
+        assert_eq!(show(negate(CPP32, &int(i32::MIN as i64))), "-2147483648");
+        assert_eq!(show(floor(CPP32, &real(-2.5))), "-3");
+
+        let formats = [(3.5, "3.5"), (1.0 / 3.0, "0.333333"), (1e6, "1e+06"), (123456789.0, "1.23457e+08"),
+                       (999999.5, "1e+06"), (100000.0, "100000"), (0.0001, "0.0001"), (0.00001234, "1.234e-05"),
+                       (-2.0, "-2"), (0.0, "0"), (1e100, "1e+100"), (f64::NEG_INFINITY, "-inf")];
+        for (x, expected) in formats {
+            assert_eq!(cout(x), expected);
+        }
+    }
+
+    #[test]
+    fn format() {
+        let third = Object::Rational(num_rational::BigRational::new(1.into(), 3.into()));
+        let booleans = Some(("DA".to_string(), "NU".to_string()));
+        let cases = [
+            (Format::default(), Object::Float(0.1 + 0.2), false, "0.30000000000000004"),
+            (Format { decimals: Some(2), ..Format::default() }, Object::Float(0.1 + 0.2), false, "0.30"),
+            (Format { decimals: Some(2), ..Format::default() }, Object::Int(7), false, "7"),
+            (Format { decimals: Some(3), comma: true, ..Format::default() }, Object::Float(2.5), false, "2,500"),
+            (Format { scientific: true, decimals: Some(2), ..Format::default() }, Object::Float(1500.0), false, "1.50e+03"),
+            (Format { scientific: true, ..Format::default() }, Object::Float(0.00025), false, "2.5e-04"),
+            (Format { decimals: Some(4), ..Format::default() }, third.clone(), false, "0.3333"),
+            (Format { fractions: Fractions::Decimal, comma: true, ..Format::default() }, third.clone(), false, "0,(3)"),
+            (Format { comma: true, ..Format::default() }, third, false, "1/3"),
+            (Format { booleans: booleans.clone(), ..Format::default() }, Object::Bool(false), true, "NU"),
+            (Format::default(), Object::Bool(true), true, "1"),
+            (Format { comma: true, ..Format::default() }, Object::String("a.b".to_string()), false, "a.b"),
+            (Format { decimals: Some(2), ..Format::default() }, Object::Float(1.0 / 3.0), true, "0.33"),
+            (Format::default(), Object::Float(1.0 / 3.0), true, "0.333333"),
+        ];
+        for (format, value, cpp, expected) in cases {
+            assert_eq!(format.show(&value, cpp), expected, "{}", value);
+        }
+        let wide = Format { decimals: Some(70000), ..Format::default() };
+        assert_eq!(wide.show(&Object::Float(0.5), false).len(), 2 + MAX_DECIMALS);
+    }
+}
@@ -1,35 +1,50 @@
 use std::{mem, fmt::Display};
+use num_bigint::BigInt;
 
-use crate::lexer::{Token, Lexer};
+use crate::lexer::{Token, Lexer, VarType};
+use crate::builtins::{self, Builtin, Stream};
 
 #[allow(dead_code)]
 #[derive(Clone, Debug, PartialEq)]
 pub enum ASTNodeValue {
     Identifier(String),
     Int(i64),
+    BigInt(BigInt),
     Float(f64),
     String(String),
     Bool(bool),
     Null,
     Program,
 
-    CastUnsignedRef(String),
-    CastFloatRef(String),
-    CastRef(String),
+    /// O variabilă cu adnotare de tip, ca în `citește n (număr natural)`.
+    Cast(String, VarType),
+    /// Fișierul din `citește din "date.in" x` sau `scrie în "date.out" x`.
+    File(String),
+    /// `x : n` în `scrie`: valoarea `x` cu `n` zecimale.
+    Decimals,
 
+    /// `a ← x` sau `a, b ← x, y`: întâi variabilele, apoi valorile, tot atâtea.
     Set,
+    /// `a ↔ b`.
+    Swap,
 
     If,
     For,
     Execute,
     While,
     Until,
+    /// `întrerupe`: iese din bucla cea mai apropiată. Păstrează cuvântul
+    /// folosit în sursă, care depinde de dialect.
+    Break(String),
+    /// `continuă`: trece la următorul pas al buclei.
+    Continue(String),
 
     Add,
     Subtract,
     Multiply,
     Divide,
     Mod,
+    Power,
 
     Not,
     And,
@@ -43,6 +58,8 @@ pub enum ASTNodeValue {
     GreaterThanEqual,
 
     Floor,
+    /// `v[i]`: tabloul, apoi indicele.
+    Index,
     FunctionCall(String),
 
     Illegal,
@@ -52,6 +69,9 @@ pub enum ASTNodeValue {
 pub struct ASTNode {
     pub value: ASTNodeValue,
     pub children: Vec<Box<ASTNode>>,
+    /// Linia din sursă la care începe instrucțiunea; 0 pentru nodurile din
+    /// interiorul expresiilor.
+    pub line: usize,
 }
 
 impl ASTNode {
@@ -60,6 +80,7 @@ impl ASTNode {
             value: match token {
                 Token::Identifier(x) => ASTNodeValue::Identifier(x),
                 Token::Int(x) => ASTNodeValue::Int(x),
+                Token::BigInt(x) => ASTNodeValue::BigInt(x),
                 Token::Float(x) => ASTNodeValue::Float(x),
                 Token::String(x) => ASTNodeValue::String(x),
                 Token::True => ASTNodeValue::Bool(true),
@@ -71,12 +92,15 @@ impl ASTNode {
                 Token::Execute => ASTNodeValue::Execute,
                 Token::While => ASTNodeValue::While,
                 Token::Until => ASTNodeValue::Until,
+                Token::Break(x) => ASTNodeValue::Break(x),
+                Token::Continue(x) => ASTNodeValue::Continue(x),
 
                 Token::Add => ASTNodeValue::Add,
                 Token::Subtract => ASTNodeValue::Subtract,
                 Token::Multiply => ASTNodeValue::Multiply,
                 Token::Divide => ASTNodeValue::Divide,
                 Token::Mod => ASTNodeValue::Mod,
+                Token::Power => ASTNodeValue::Power,
 
                 Token::Not => ASTNodeValue::Not,
                 Token::And => ASTNodeValue::And,
@@ -89,21 +113,26 @@ impl ASTNode {
                 Token::LessThanEqual => ASTNodeValue::LessThanEqual,
                 Token::GreaterThanEqual => ASTNodeValue::GreaterThanEqual,
 
-                Token::Illegal => ASTNodeValue::Illegal,
-
-                _ => unreachable!("Unimplemented token: {}", token)
+                _ => ASTNodeValue::Illegal,
             },
-            children: Vec::new()
+            children: Vec::new(),
+            line: 0,
         }
     }
 
     pub fn from(value: ASTNodeValue) -> ASTNode {
         ASTNode {
             value,
-            children: Vec::new()
+            children: Vec::new(),
+            line: 0,
         }
     }
 
+    /// Variabilele și valorile unei atribuiri `Set`.
+    pub fn assignment(&self) -> (&[Box<ASTNode>], &[Box<ASTNode>]) {
+        self.children.split_at(self.children.len() / 2)
+    }
+
     #[allow(dead_code)]
     pub fn print(&self, mut ident: String, last: bool) {
         print!("{}", ident);
@@ -117,29 +146,34 @@ impl ASTNode {
         println!(" {}", match &self.value {
             ASTNodeValue::Identifier(x) => format!("Identifier \x1b[40G{}", x),
             ASTNodeValue::Int(x) => format!("Int \x1b[40G{}", x),
+            ASTNodeValue::BigInt(x) => format!("BigInt \x1b[40G{}", x),
             ASTNodeValue::Float(x) => format!("Float \x1b[40G{}", x),
             ASTNodeValue::String(x) => format!("String \x1b[40G{}", x),
             ASTNodeValue::Bool(x) => format!("Bool \x1b[40G{}", x),
             ASTNodeValue::Null => "Null".to_string(),
             ASTNodeValue::Program => "Program".to_string(),
 
-            ASTNodeValue::CastUnsignedRef(x) => format!("CastUnsignedRef \x1b[40G{}", x),
-            ASTNodeValue::CastFloatRef(x) => format!("CastFloatRef \x1b[40G{}", x),
-            ASTNodeValue::CastRef(x) => format!("CastRef \x1b[40G{}", x),
+            ASTNodeValue::Cast(x, t) => format!("Cast \x1b[40G{} ({})", x, t),
+            ASTNodeValue::File(x) => format!("File \x1b[40G{}", x),
+            ASTNodeValue::Decimals => "Decimals".to_string(),
 
             ASTNodeValue::Set => "Set".to_string(),
+            ASTNodeValue::Swap => "Swap".to_string(),
 
             ASTNodeValue::If => "If".to_string(),
             ASTNodeValue::For => "For".to_string(),
             ASTNodeValue::Execute => "Execute".to_string(),
             ASTNodeValue::While => "While".to_string(),
             ASTNodeValue::Until => "Until".to_string(),
+            ASTNodeValue::Break(x) => format!("Break \x1b[40G{}", x),
+            ASTNodeValue::Continue(x) => format!("Continue \x1b[40G{}", x),
 
             ASTNodeValue::Add => "Add".to_string(),
             ASTNodeValue::Subtract => "Subtract".to_string(),
             ASTNodeValue::Multiply => "Multiply".to_string(),
             ASTNodeValue::Divide => "Divide".to_string(),
             ASTNodeValue::Mod => "Mod".to_string(),
+            ASTNodeValue::Power => "Power".to_string(),
 
             ASTNodeValue::Not => "Not".to_string(),
             ASTNodeValue::And => "And".to_string(),
@@ -153,6 +187,7 @@ impl ASTNode {
             ASTNodeValue::GreaterThanEqual => "GreaterThanEqual".to_string(),
 
             ASTNodeValue::Floor => "Floor".to_string(),
+            ASTNodeValue::Index => "Index".to_string(),
             ASTNodeValue::FunctionCall(x) => format!("FunctionCall\x1b[40G{}", x),
 
             ASTNodeValue::Illegal => "Illegal".to_string(),
@@ -170,29 +205,34 @@ impl Display for ASTNodeValue {
         match self {
             ASTNodeValue::Identifier(x) => write!(f, "Identifier({})", x),
             ASTNodeValue::Int(x) => write!(f, "Int({})", x),
+            ASTNodeValue::BigInt(x) => write!(f, "BigInt({})", x),
             ASTNodeValue::Float(x) => write!(f, "Float({})", x),
             ASTNodeValue::String(x) => write!(f, "String({})", x),
             ASTNodeValue::Bool(x) => write!(f, "Bool({})", x),
             ASTNodeValue::Null => write!(f, "Null"),
             ASTNodeValue::Program => write!(f, "Program"),
 
-            ASTNodeValue::CastUnsignedRef(x) => write!(f, "CastUnsignedRef({})", x),
-            ASTNodeValue::CastFloatRef(x) => write!(f, "CastFloatRef({})", x),
-            ASTNodeValue::CastRef(x) => write!(f, "CastRef({})", x),
+            ASTNodeValue::Cast(x, t) => write!(f, "Cast({}, {})", x, t),
+            ASTNodeValue::File(x) => write!(f, "File({})", x),
+            ASTNodeValue::Decimals => write!(f, "Decimals"),
 
             ASTNodeValue::Set => write!(f, "Set"),
+            ASTNodeValue::Swap => write!(f, "Swap"),
 
             ASTNodeValue::If => write!(f, "If"),
             ASTNodeValue::For => write!(f, "For"),
             ASTNodeValue::Execute => write!(f, "Execute"),
             ASTNodeValue::While => write!(f, "While"),
             ASTNodeValue::Until => write!(f, "Until"),
+            ASTNodeValue::Break(x) => write!(f, "Break({})", x),
+            ASTNodeValue::Continue(x) => write!(f, "Continue({})", x),
 
             ASTNodeValue::Add => write!(f, "Add"),
             ASTNodeValue::Subtract => write!(f, "Subtract"),
             ASTNodeValue::Multiply => write!(f, "Multiply"),
             ASTNodeValue::Divide => write!(f, "Divide"),
             ASTNodeValue::Mod => write!(f, "Mod"),
+            ASTNodeValue::Power => write!(f, "Power"),
 
             ASTNodeValue::Not => write!(f, "Not"),
             ASTNodeValue::And => write!(f, "And"),
@@ -206,6 +246,7 @@ impl Display for ASTNodeValue {
             ASTNodeValue::GreaterThanEqual => write!(f, "GreaterThanEqual"),
 
             ASTNodeValue::Floor => write!(f, "Floor"),
+            ASTNodeValue::Index => write!(f, "Index"),
             ASTNodeValue::FunctionCall(x) => write!(f, "FunctionCall({})", x),
 
             ASTNodeValue::Illegal => write!(f, "Illegal"),
@@ -213,11 +254,43 @@ impl Display for ASTNodeValue {
     }
 }
 
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "linia {}: {}", self.line, self.message)
+    }
+}
+
+/// Într-un `citește a, b (numere naturale)`, adnotarea de după o listă se
+/// aplică tuturor variabilelor fără adnotare dinaintea ei.
+fn annotate_list(args: &mut [Box<ASTNode>]) {
+    let mut start = 0;
+    for i in 0..args.len() {
+        if let ASTNodeValue::Cast(_, t) = args[i].value {
+            for arg in &mut args[start..i] {
+                if let ASTNodeValue::Identifier(name) = &arg.value {
+                    arg.value = ASTNodeValue::Cast(name.clone(), t);
+                }
+            }
+            start = i + 1;
+        }
+    }
+}
+
 pub struct Parser {
     pub lexer: Lexer,
     c: Token,
     n: Token,
-    pub errors: Vec<String>,
+    line: usize,
+    n_line: usize,
+    /// Câte bucle cuprind instrucțiunea curentă.
+    loops: usize,
+    pub errors: Vec<ParseError>,
 }
 
 impl Parser {
@@ -226,6 +299,9 @@ impl Parser {
             lexer,
             c: Token::Illegal,
             n: Token::Illegal,
+            line: 0,
+            n_line: 0,
+            loops: 0,
             errors: Vec::new()
         };
 
@@ -235,20 +311,26 @@ impl Parser {
         parser
     }
 
-    fn next(&mut self, skip_scolon: bool) {
-        if self.c == Token::SColon && skip_scolon {
-            self.c = self.n.clone();
-            self.n = self.lexer.next().unwrap_or_else(|err| {
-                self.errors.push(err.to_string());
-                Token::Illegal
-            });
-        }
-
+    fn advance(&mut self) {
         self.c = self.n.clone();
+        self.line = self.n_line;
         self.n = self.lexer.next().unwrap_or_else(|err| {
-            self.errors.push(err.to_string());
+            self.errors.push(ParseError { line: self.lexer.tok_line, message: err.to_string() });
             Token::Illegal
         });
+        self.n_line = self.lexer.tok_line;
+    }
+
+    fn next(&mut self, skip_scolon: bool) {
+        if self.c == Token::SColon && skip_scolon {
+            self.advance();
+        }
+
+        self.advance();
+    }
+
+    fn error(&mut self, message: String) {
+        self.errors.push(ParseError { line: self.line, message });
     }
 
     fn next_prev(&mut self, skip_scolon: bool) -> Token {
@@ -278,52 +360,91 @@ impl Parser {
         if self.accept(ttype.clone(), skip_scolon) {
             return true;
         }
-        self.errors.push(format!("Se aștepta {}, s-a primit {}", ttype, self.c).to_string());
+        self.error(format!("Se aștepta {}, s-a primit {}", ttype, self.c));
         false
     }
 
     fn function_call(&mut self, in_prog: bool) -> Box<ASTNode> {
         let mut node = ASTNode::from(ASTNodeValue::FunctionCall(match &self.next_prev(false) {
             Token::Identifier(x) => x.to_string(),
-            _ => unreachable!()
+            token => {
+                self.error(format!("Se aștepta numele unei funcții, s-a primit {}", token));
+                return Box::new(ASTNode::from_token(Token::Illegal));
+            }
         }));
+        let builtin = match &node.value {
+            ASTNodeValue::FunctionCall(name) => builtins::lookup(name),
+            _ => None,
+        };
         if in_prog {
+            // `citește din "date.in" ...`, `scrie în "date.out" ...`
+            if let Some((keyword, _)) = builtin.and_then(|b| b.file)
+                && let Token::Identifier(x) = &self.c && x == keyword && let Token::String(path) = &self.n {
+                node.children.push(Box::new(ASTNode::from(ASTNodeValue::File(path.clone()))));
+                self.next(false);
+                self.next(false);
+            }
             while !matches!(self.c, Token::SColon | Token::EOF) {
-                node.children.push(self.expr());
+                node.children.push(self.argument(builtin));
                 if !matches!(self.c, Token::SColon | Token::EOF) {
                     self.expect(Token::Comma, false);
                 }
             }
+            if builtin.is_some_and(|b| b.assigns()) {
+                annotate_list(&mut node.children);
+            }
         } else {
             self.expect(Token::LParen, false);
-            while !self.is(Token::RParen) {
-                node.children.push(self.expr());
+            while !matches!(self.c, Token::RParen | Token::EOF) {
+                node.children.push(self.argument(builtin));
                 if !self.is(Token::RParen) {
                     self.expect(Token::Comma, false);
                 }
             }
             self.expect(Token::RParen, false);
         }
+        if let Some(b) = builtin && b.assigns() {
+            for arg in &node.children {
+                if let ASTNodeValue::Identifier(x) | ASTNodeValue::Cast(x, _) = &arg.value {
+                    self.check_variable(x);
+                }
+            }
+        }
+        Box::new(node)
+    }
+
+    /// Un argument al unui apel; `scrie` acceptă și `x : n`, cu `n` zecimale.
+    fn argument(&mut self, builtin: Option<&'static Builtin>) -> Box<ASTNode> {
+        let value = self.expr();
+        if !self.is(Token::Colon) {
+            return value;
+        }
+        if !builtin.is_some_and(|b| matches!(b.file, Some((_, Stream::Output)))) {
+            self.error("Precizia `: n` poate apărea doar în argumentele lui `scrie`.".to_string());
+        }
+        self.next(false);
+        let mut node = ASTNode::from(ASTNodeValue::Decimals);
+        node.children.push(value);
+        node.children.push(self.expr());
         Box::new(node)
     }
 
     fn factor(&mut self) -> Box<ASTNode> {
         if self.is(Token::Identifier(String::new())) && self.n == Token::LParen {
             self.function_call(false)
-        } else if let Token::Identifier(name) = &self.c && matches!(self.n, Token::CastUnsignedRef | Token::CastFloatRef | Token::CastRef) {
-            let ret = Box::new(match self.n {
-                Token::CastUnsignedRef => ASTNode::from(ASTNodeValue::CastUnsignedRef(name.to_string())),
-                Token::CastFloatRef => ASTNode::from(ASTNodeValue::CastFloatRef(name.to_string())),
-                Token::CastRef => ASTNode::from(ASTNodeValue::CastRef(name.to_string())),
-                _ => unreachable!(),
-            });
+        } else if let Token::Identifier(name) = &self.c && let Token::Annotation(t) = self.n {
+            let ret = Box::new(ASTNode::from(ASTNodeValue::Cast(name.to_string(), t)));
             self.next(false);
             self.next(false);
             ret
-        } else if self.is(Token::Identifier(String::new())) || self.is(Token::Int(0)) || self.is(Token::Float(0.0)) || self.is(Token::Null) || self.is(Token::False) || self.is(Token::True) || self.is(Token::String(String::new())) {
+        } else if let Token::Annotation(t) = self.c {
+            self.error(format!("Adnotarea `({})` trebuie să urmeze după o variabilă.", t));
+            self.next(true);
+            Box::new(ASTNode::from_token(Token::Illegal))
+        } else if self.is(Token::Identifier(String::new())) || self.is(Token::Int(0)) || self.is(Token::BigInt(BigInt::default())) || self.is(Token::Float(0.0)) || self.is(Token::Null) || self.is(Token::False) || self.is(Token::True) || self.is(Token::String(String::new())) {
             let ret = Box::new(ASTNode::from_token(self.c.clone()));
             self.next(true);
-            ret
+            self.indices(ret)
         } else if self.accept(Token::LParen, true) {
             let ret = self.math_expr();
             self.expect(Token::RParen, true);
@@ -333,13 +454,57 @@ impl Parser {
             node.children.push(self.math_expr());
             self.expect(Token::FloorEnd, true);
             Box::new(node)
+        } else if self.accept(Token::Pipe, true) {
+            let mut node = ASTNode::from(ASTNodeValue::FunctionCall("abs".to_string()));
+            node.children.push(self.math_expr());
+            self.expect(Token::Pipe, true);
+            Box::new(node)
         } else {
-            self.errors.push(format!("Illegal token: {}", self.c));
+            self.error(format!("Illegal token: {}", self.c));
             self.next(true);
             Box::new(ASTNode::from_token(Token::Illegal))
         }
     }
 
+    /// Puterea leagă mai strâns decât înmulțirea și se grupează la dreapta:
+    /// `2 ^ 3 ^ 2` este `2 ^ 9`. `√` se aplică puterii care urmează.
+    fn power(&mut self) -> Box<ASTNode> {
+        if self.accept(Token::Sqrt, true) {
+            let mut node = ASTNode::from(ASTNodeValue::FunctionCall("sqrt".to_string()));
+            node.children.push(self.power());
+            return Box::new(node);
+        }
+        let base = self.factor();
+        let exponent = if let Token::Superscript(text) = &self.c {
+            let text = text.clone();
+            self.next(true);
+            let mut parser = Parser::new(Lexer::new(text.clone()));
+            let exponent = parser.expr();
+            if !parser.errors.is_empty() || parser.c != Token::EOF {
+                self.error(format!("Exponent invalid: `{}`.", text));
+            }
+            exponent
+        } else if self.accept(Token::Power, true) {
+            // `2 ^ -n`
+            if self.is(Token::Subtract) {
+                let mut negative = ASTNode::from_token(self.next_prev(true));
+                negative.children.push(self.power());
+                Box::new(negative)
+            } else {
+                self.power()
+            }
+        } else {
+            return base;
+        };
+        if exponent.value == ASTNodeValue::Illegal {
+            self.error(String::from("Missing expression"));
+        }
+        let mut node = ASTNode::from(ASTNodeValue::Power);
+        node.children.push(base);
+        node.children.push(exponent);
+        Box::new(node)
+    }
+
     fn term(&mut self) -> Box<ASTNode> {
         let mut node: Box<ASTNode>;
         if self.is(Token::Subtract) || self.is(Token::Not) {
@@ -347,14 +512,14 @@ impl Parser {
             node.children.push(self.term());
             return node;
         } else {
-            node = self.factor();
+            node = self.power();
         }
 
         while matches!(self.c, Token::Multiply | Token::Divide | Token::Mod) {
             let mut new = ASTNode::from_token(self.next_prev(true));
-            let right = self.factor();
+            let right = self.power();
             if right.value == ASTNodeValue::Illegal {
-                self.errors.push(String::from("Missing expression"));
+                self.error(String::from("Missing expression"));
                 break;
             }
             new.children.push(node);
@@ -371,7 +536,7 @@ impl Parser {
             let mut new = ASTNode::from_token(self.next_prev(true));
             let right = self.math_expr();
             if right.value == ASTNodeValue::Illegal {
-                self.errors.push(String::from("Missing expression"));
+                self.error(String::from("Missing expression"));
                 break;
             }
             new.children.push(node);
@@ -394,7 +559,7 @@ impl Parser {
             let mut new = ASTNode::from_token(self.next_prev(true));
             let right = self.logical_expr();
             if right.value == ASTNodeValue::Illegal {
-                self.errors.push(String::from("Missing expression"));
+                self.error(String::from("Missing expression"));
                 break;
             }
             new.children.push(node);
@@ -409,36 +574,54 @@ impl Parser {
         ret
     }
 
+    /// `dacă c₁ atunci ... altfel dacă c₂ atunci ... altfel ... ■`, ca un
+    /// singur nod: perechi (condiție, ramură), apoi ramura `altfel`, dacă
+    /// există.
     fn pif(&mut self) -> Box<ASTNode> {
         self.expect(Token::If, true);
         let mut node = ASTNode::from_token(Token::If);
 
-        let expr = self.expr();
-        if expr.value == ASTNodeValue::Illegal {
-            self.errors.push(String::from("Se aștepta o condiție"));
-            return Box::new(ASTNode::from_token(Token::Illegal));
-        }
+        loop {
+            let line = self.line;
+            let mut expr = self.expr();
+            if expr.value == ASTNodeValue::Illegal {
+                self.error(String::from("Se aștepta o condiție"));
+                return Box::new(ASTNode::from_token(Token::Illegal));
+            }
+            expr.line = line;
 
-        node.children.push(expr);
-        self.expect(Token::Then, true);
+            node.children.push(expr);
+            self.expect(Token::Then, true);
 
-        node.children.push(self.prog(true, true, false));
+            node.children.push(self.prog(true, true, false));
 
-        if self.accept(Token::Else, true) {
-            node.children.push(self.prog(true, false, false));
+            if !self.accept(Token::Else, true) {
+                break;
+            }
+            // `altfel dacă` pe același rând continuă lanțul, cu un singur `■`.
+            if !self.accept(Token::If, true) {
+                node.children.push(self.prog(true, false, false));
+                break;
+            }
         }
 
         Box::new(node)
     }
 
     fn pforheader(&mut self) -> Box<ASTNode> {
+        let line = self.line;
         self.expect(Token::For, true);
         let mut node = ASTNode::from_token(Token::For);
+        node.line = line;
         if !self.is(Token::Identifier(String::new())) && self.n != Token::Set {
-            self.errors.push(String::from("Se aștepta o declarație de variabilă."));
+            self.error(String::from("Se aștepta o declarație de variabilă."));
             return Box::new(ASTNode::from_token(Token::Illegal));
         }
         let set = self.set();
+        if set.children.len() != 2 {
+            self.error(String::from("`pentru` are o singură variabilă contor."));
+            return Box::new(ASTNode::from_token(Token::Illegal));
+        }
         node.children.push(set);
         if !self.expect(Token::Comma, false) {
             return Box::new(ASTNode::from_token(Token::Illegal));
@@ -451,23 +634,46 @@ impl Parser {
     }
 
     fn pwhileheader(&mut self) -> Box<ASTNode> {
+        let line = self.line;
         self.expect(Token::While, true);
         let mut node = ASTNode::from_token(Token::While);
+        node.line = line;
         node.children.push(self.stmt());
         Box::new(node)
     }
 
     fn puntilheader(&mut self) -> Box<ASTNode> {
+        let line = self.line;
         self.expect(Token::Until, true);
         let mut node = ASTNode::from_token(Token::Until);
+        node.line = line;
         node.children.push(self.stmt());
         Box::new(node)
     }
 
+    fn loop_body(&mut self, in_execute: bool) -> Box<ASTNode> {
+        self.loops += 1;
+        let body = self.prog(true, false, in_execute);
+        self.loops -= 1;
+        body
+    }
+
+    /// `întrerupe` sau `continuă`, care au sens doar într-o buclă.
+    fn loop_control(&mut self) -> Box<ASTNode> {
+        let token = self.next_prev(false);
+        if let Token::Break(name) | Token::Continue(name) = &token && self.loops == 0 {
+            self.error(format!("`{}` poate apărea doar într-o buclă.", name));
+        }
+        Box::new(ASTNode::from_token(token))
+    }
+
     fn execute(&mut self) -> Box<ASTNode> {
-        self.expect(Token::Execute, true);
+        // `repetă ... până când` este la fel ca `execută ... până când`.
+        if !self.accept(Token::Repeat, true) {
+            self.expect(Token::Execute, true);
+        }
         let mut node = ASTNode::from_token(Token::Execute);
-        node.children.push(self.prog(true, false, true));
+        node.children.push(self.loop_body(true));
         if self.is(Token::While) {
             node.children.push(self.pwhileheader());
         } else if self.is(Token::Until) {
@@ -481,37 +687,125 @@ impl Parser {
     fn pfor(&mut self) -> Box<ASTNode> {
         let mut node = self.pforheader();
         self.expect(Token::Execute, true);
-        node.children.push(self.prog(true, false, false));
+        node.children.push(self.loop_body(false));
         node
     }
 
     fn pwhile(&mut self) -> Box<ASTNode> {
         let mut node = self.pwhileheader();
         self.expect(Token::Execute, true);
-        node.children.push(self.prog(true, false, false));
+        node.children.push(self.loop_body(false));
         node
     }
 
     fn until(&mut self) -> Box<ASTNode> {
         let mut node = self.puntilheader();
         self.expect(Token::Execute, true);
-        node.children.push(self.prog(true, false, false));
+        node.children.push(self.loop_body(false));
+        node
+    }
+
+    /// Numele funcțiilor predefinite nu pot fi folosite ca variabile.
+    fn check_variable(&mut self, name: &str) {
+        if builtins::is_builtin(name) {
+            self.error(format!("`{}` este o funcție predefinită și nu poate fi folosită ca variabilă.", name));
+        }
+    }
+
+    /// Indicii de după un nume: `v[i]`, `a[i][j]`.
+    fn indices(&mut self, mut node: Box<ASTNode>) -> Box<ASTNode> {
+        while self.accept(Token::IndexStart, true) {
+            let mut index = ASTNode::from(ASTNodeValue::Index);
+            index.children.push(node);
+            index.children.push(self.math_expr());
+            self.expect(Token::FloorEnd, true);
+            node = Box::new(index);
+        }
         node
     }
 
+    /// O variabilă sau un element de tablou care primește o valoare.
+    fn target(&mut self) -> Box<ASTNode> {
+        if !self.is(Token::Identifier(String::new())) {
+            self.error(String::from("Se poate atribui doar unei variabile."));
+            return Box::new(ASTNode::from_token(Token::Illegal));
+        }
+        let name = Box::new(ASTNode::from_token(self.next_prev(true)));
+        let target = self.indices(name);
+        self.check_target(&target);
+        target
+    }
+
+    /// Numele din `v` sau `v[i][j]` nu poate fi al unei funcții predefinite.
+    fn check_target(&mut self, mut target: &ASTNode) {
+        while target.value == ASTNodeValue::Index && let Some(base) = target.children.first() {
+            target = base;
+        }
+        if let ASTNodeValue::Identifier(x) = &target.value {
+            let x = x.clone();
+            self.check_variable(&x);
+        }
+    }
+
+    /// `a ← x` sau atribuirea paralelă `a, b ← b, a`, cu câte o valoare
+    /// pentru fiecare variabilă.
     fn set(&mut self) -> Box<ASTNode> {
-        let identifier = ASTNode::from_token(self.next_prev(true));
-        self.expect(Token::Set, false);
+        let first = self.target();
+        self.set_from(first)
+    }
+
+    /// Restul unei atribuiri, după prima variabilă.
+    fn set_from(&mut self, first: Box<ASTNode>) -> Box<ASTNode> {
+        let line = self.line;
         let mut node = ASTNode::from(ASTNodeValue::Set);
-        node.children.push(Box::new(identifier));
+        node.line = line;
+        node.children.push(first);
+        while self.accept(Token::Comma, false) {
+            node.children.push(self.target());
+        }
+        let targets = node.children.len();
+        self.expect(Token::Set, false);
         node.children.push(self.expr());
+        for _ in 1..targets {
+            if !self.accept(Token::Comma, false) {
+                let values = node.children.len() - targets;
+                self.error(format!("Atribuirea are {} variabile, dar {} {}.", targets, values, if values == 1 { "valoare" } else { "valori" }));
+                return Box::new(ASTNode::from_token(Token::Illegal));
+            }
+            node.children.push(self.expr());
+        }
+        Box::new(node)
+    }
+
+    /// `a ↔ b`.
+    fn swap(&mut self) -> Box<ASTNode> {
+        let first = self.target();
+        self.swap_from(first)
+    }
+
+    fn swap_from(&mut self, first: Box<ASTNode>) -> Box<ASTNode> {
+        let line = self.line;
+        let mut node = ASTNode::from(ASTNodeValue::Swap);
+        node.line = line;
+        node.children.push(first);
+        self.expect(Token::Swap, false);
+        node.children.push(self.target());
         Box::new(node)
     }
 
     fn stmt(&mut self) -> Box<ASTNode> {
+        let line = self.line;
+        let mut node = self.stmt_inner();
+        if node.line == 0 {
+            node.line = line;
+        }
+        node
+    }
+
+    fn stmt_inner(&mut self) -> Box<ASTNode> {
         if self.is(Token::If) {
             self.pif()
-        } else if self.is(Token::Execute) {
+        } else if self.is(Token::Execute) || self.is(Token::Repeat) {
             self.execute()
         } else if self.is(Token::For) {
             self.pfor()
@@ -519,8 +813,26 @@ impl Parser {
             self.pwhile()
         } else if self.is(Token::Until) {
             self.until()
-        } else if self.is(Token::Identifier(String::new())) && self.n == Token::Set {
+        } else if matches!(self.c, Token::Break(_) | Token::Continue(_)) {
+            self.loop_control()
+        } else if self.is(Token::Identifier(String::new())) && matches!(self.n, Token::Set | Token::Comma) {
             self.set()
+        } else if self.is(Token::Identifier(String::new())) && self.n == Token::Swap {
+            self.swap()
+        } else if self.is(Token::Identifier(String::new())) && self.n == Token::IndexStart {
+            // `v[i] ← x` sau doar expresia `v[i] + 1`, după ce urmează.
+            let first = self.expr();
+            match self.c {
+                Token::Set | Token::Comma if first.value == ASTNodeValue::Index => {
+                    self.check_target(&first);
+                    self.set_from(first)
+                }
+                Token::Swap if first.value == ASTNodeValue::Index => {
+                    self.check_target(&first);
+                    self.swap_from(first)
+                }
+                _ => first,
+            }
         } else {
             self.expr()
         }
@@ -535,9 +847,15 @@ impl Parser {
             if self.is(Token::EOF) {
                 break;
             }
+            if in_block && self.is(Token::BlockEnd) {
+                self.next(false);
+                break;
+            }
 
-            let stmt = if self.is(Token::Identifier(String::new())) && (self.pis(Token::Identifier(String::new())) || self.pis(Token::Int(0)) || self.pis(Token::Float(0.0)) || self.pis(Token::String(String::new())) || self.pis(Token::True) || self.pis(Token::False) || self.pis(Token::Null)) && !matches!(self.c, Token::If | Token::Execute | Token::For | Token::While | Token::Until) {
-                let res = self.function_call(true);
+            let stmt = if self.is(Token::Identifier(String::new())) && (self.pis(Token::Identifier(String::new())) || self.pis(Token::Int(0)) || self.pis(Token::BigInt(BigInt::default())) || self.pis(Token::Float(0.0)) || self.pis(Token::String(String::new())) || self.pis(Token::True) || self.pis(Token::False) || self.pis(Token::Null) || self.pis(Token::Sqrt) || self.pis(Token::Pipe)) && !matches!(self.c, Token::If | Token::Execute | Token::For | Token::While | Token::Until) {
+                let line = self.line;
+                let mut res = self.function_call(true);
+                res.line = line;
                 self.expect(Token::SColon, false);
                 res
             } else {
@@ -564,3 +882,67 @@ impl Parser {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::Lexer;
+
+    use super::{ASTNodeValue, Parser};
+
+    #[test]
+    fn statement_lines() {
+        let mut parser = Parser::new(Lexer::new(String::from("a <- 1\n\ncât timp a < 3 execută\n    a <- a + 1\n■\nscrie a;")));
+        let prog = parser.parse();
+        assert!(parser.errors.is_empty());
+        let lines: Vec<usize> = prog.children.iter().map(|s| s.line).collect();
+        assert_eq!(lines, vec![1, 3, 6]);
+        assert_eq!(prog.children[1].children[1].children[0].line, 4);
+    }
+
+    #[test]
+    fn empty_block() {
+        let mut parser = Parser::new(Lexer::new(String::from("cât timp a < 3 execută\n■\nb <- 2")));
+        let prog = parser.parse();
+        assert!(parser.errors.is_empty());
+        assert_eq!(prog.children.len(), 2);
+        assert!(prog.children[0].children[1].children.is_empty());
+        assert_eq!(prog.children[1].value, ASTNodeValue::Set);
+    }
+
+    #[test]
+    fn unclosed_call() {
+        let mut parser = Parser::new(Lexer::new(String::from("scrie (")));
+        parser.parse();
+        assert!(!parser.errors.is_empty());
+    }
+
+    #[test]
+    fn leading_zero() {
+        let mut parser = Parser::new(Lexer::new(String::from("x <- 01\nscrie x\n")));
+        parser.parse();
+        assert_eq!(parser.errors[0].line, 1);
+        assert_eq!(parser.errors[0].message, "Invalid number literal");
+    }
+
+    #[test]
+    fn indexed_targets() {
+        let mut parser = Parser::new(Lexer::new(String::from("v[i] <- 1
+a, v[i + 1] <- v[i], a
+v[1] ↔ m[2][j]
+x <- 2 * [a / 2]
+v[2] + 1
+")));
+        let prog = parser.parse();
+        assert!(parser.errors.is_empty(), "{:?}", parser.errors);
+        let values: Vec<_> = prog.children.iter().map(|s| s.value.clone()).collect();
+        assert_eq!(values, vec![ASTNodeValue::Set, ASTNodeValue::Set, ASTNodeValue::Swap, ASTNodeValue::Set, ASTNodeValue::Add]);
+        assert_eq!(prog.children[1].children[1].value, ASTNodeValue::Index);
+        assert_eq!(prog.children[2].children[1].children[0].value, ASTNodeValue::Index);
+        assert_eq!(prog.children[3].children[1].children[1].value, ASTNodeValue::Floor);
+
+        let mut parser = Parser::new(Lexer::new(String::from("max[1] <- 2
+")));
+        parser.parse();
+        assert_eq!(parser.errors[0].message, "`max` este o funcție predefinită și nu poate fi folosită ca variabilă.");
+    }
+}
@@ -1,14 +1,16 @@
 use rustyline::{Editor, history::DefaultHistory, validate::Validator, hint::{Hinter, HistoryHinter}, Context, highlight::Highlighter, Helper, completion::{Completer, Pair}, error::ReadlineError, config::Configurer};
 use crossterm::cursor;
 use home::home_dir;
-use std::{rc::Rc, cell::RefCell};
+use std::{rc::Rc, cell::RefCell, sync::{Arc, atomic::{AtomicBool, Ordering}}};
 use std::path::Path;
 use anyhow::{anyhow, Result};
 
-use crate::interpreter::{Environment, eval};
-use crate::lexer::Lexer;
+use crate::interpreter::{Environment, Limits, Interrupted, eval};
+use crate::lexer::{Dialect, Lexer};
 use crate::parser::Parser;
+use crate::types::infer_with;
 use crate::interpreter::Object;
+use crate::ops::{Arithmetic, Format};
 
 pub struct CustomHelper {
     pub hinter: HistoryHinter,
@@ -29,7 +31,7 @@ impl Completer for CustomHelper {
         let word = &line[word_start..pos];
 
         if !word.is_empty() {
-            for i in vec!["dacă", "până când", "pânăcând", "cât timp", "câttimp", "atunci", "altfel", "pentru", "execută", "scrie", "citește"] {
+            for i in vec!["dacă", "până când", "pânăcând", "cât timp", "câttimp", "atunci", "altfel", "pentru", "execută", "repetă", "întrerupe", "continuă", "scrie", "citește"] {
                 if i.starts_with(word) {
                     completions.push(Pair {
                         display: i.to_string(),
@@ -52,26 +54,36 @@ impl Hinter for CustomHelper {
 
 pub type RoLangReadlineEditor = Editor<CustomHelper, DefaultHistory>;
 
-pub fn exec(input: &str) -> Result<Object> {
-    let envb = Rc::new(RefCell::new(Environment::new()));
-
-    let lex = Lexer::new(input.to_string());
+/// Rulează un program. Erorile de sintaxă și de tip se întorc în `Err`, nu
+/// se scriu în `env.output`, care rămâne doar pentru ce scrie programul.
+pub fn exec(input: &str, envb: Rc<RefCell<Environment>>) -> Result<Object> {
+    let lex = Lexer::with_dialect(input.to_string(), envb.borrow().dialect.clone());
     let mut parser = Parser::new(lex);
     let root = parser.parse();
     //root.print(String::new(), true);
 
-    if parser.errors.len() > 0 {
-        println!("Erori găsite:");
+    if !parser.errors.is_empty() {
+        let mut message = String::from("Erori găsite:");
         for (pos, msg) in parser.errors.iter().enumerate() {
-            println!(" {}: {}", pos+1, msg);
+            message.push_str(&format!("\n {}: {}", pos + 1, msg));
+        }
+        return Err(anyhow!(message));
+    }
+
+    let types = infer_with(&root, envb.borrow().arithmetic);
+    if !types.errors.is_empty() {
+        let mut message = String::from("Erori de tip găsite:");
+        for (pos, err) in types.errors.iter().enumerate() {
+            message.push_str(&format!("\n {}: linia {}: {}", pos + 1, err.line, err.message));
         }
-        return Err(anyhow!("Există erori."));
+        return Err(anyhow!(message));
     }
 
+    envb.borrow_mut().reset_usage();
     eval(root, Some(Rc::clone(&envb)))
 }
 
-pub fn repl() {
+pub fn repl(limits: Limits, arithmetic: Arithmetic, format: Format, dialect: Dialect) {
     let mut rl_hist = home_dir().unwrap();
     rl_hist.push(Path::new(".rolang_history"));
     let rl_hist = rl_hist.to_str().unwrap();
@@ -84,12 +96,27 @@ pub fn repl() {
     rl.set_helper(Some(CustomHelper { hinter: HistoryHinter {  }}));
     rl.set_completion_type(rustyline::CompletionType::Circular);
 
+    // Ctrl-C în timpul unui program ridică doar steagul; programul se oprește
+    // la următorul pas, iar sesiunea și istoricul rămân.
+    let interrupt = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&interrupt);
+    if ctrlc::set_handler(move || flag.store(true, Ordering::SeqCst)).is_err() {
+        println!("Nu s-a putut instala handler-ul pentru Ctrl-C.");
+    }
+
+    let envb = Rc::new(RefCell::new(Environment::new()));
+    envb.borrow_mut().limits = limits;
+    envb.borrow_mut().arithmetic = arithmetic;
+    envb.borrow_mut().format = format;
+    envb.borrow_mut().dialect = dialect;
+    envb.borrow_mut().interrupt = Some(Arc::clone(&interrupt));
+
     loop {
-        let readline = rl.readline("> ");
-        if readline.is_err() {
-            break;
-        }
-        let mut input = readline.unwrap();
+        let mut input = match rl.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(_) => break,
+        };
 
         if input.trim().is_empty() {
             continue;
@@ -99,7 +126,8 @@ pub fn repl() {
 
         input.push('\n');
 
-        let res = exec(&input);
+        interrupt.store(false, Ordering::SeqCst);
+        let res = exec(&input, Rc::clone(&envb));
         match cursor::position() {
             Ok((x, _)) => if x != 0 {
                 println!("\x1b[7m%\x1b[0m");
@@ -107,9 +135,10 @@ pub fn repl() {
             Err(_) => ()
         };
         match res {
+            Err(e) if e.is::<Interrupted>() => println!("{}", e),
             Err(e) => println!("Eroare ROLang: {}", e),
             Ok(res) => if res != Object::Null {
-                println!("Rezultat: {}", res);
+                println!("Rezultat: {}", envb.borrow().show(&res, None));
             }
         }
     }
@@ -117,3 +146,24 @@ pub fn repl() {
     rl.save_history(rl_hist).unwrap();
 
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, cell::RefCell};
+
+    use super::exec;
+    use crate::interpreter::Environment;
+    use crate::testing::Shared;
+
+    #[test]
+    fn errors_stay_out_of_the_output() {
+        for (program, expected) in [("x <- (\n", "Erori găsite:\n 1: linia 1: "), ("s <- 'a'\nx <- s - 1\n", "Erori de tip găsite:\n 1: linia 2: ")] {
+            let out = Shared::default();
+            let envb = Rc::new(RefCell::new(Environment::new()));
+            envb.borrow_mut().output = Box::new(out.clone());
+            let error = exec(program, envb).err().unwrap().to_string();
+            assert!(error.starts_with(expected), "{}", error);
+            assert!(out.0.borrow().is_empty());
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use std::io::{BufRead, Write};
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+/// Citește un mesaj încadrat cu antetul `Content-Length`, ca în protocoalele
+/// DAP și LSP. Întoarce `None` la sfârșitul intrării.
+pub fn read_message(input: &mut dyn BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') && name.eq_ignore_ascii_case("content-length") {
+            length = Some(value.trim().parse::<usize>()?);
+        }
+    }
+
+    let mut body = vec![0; length.unwrap()];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(|e| anyhow!("Mesaj JSON invalid: {}", e))
+}
+
+pub fn write_message(output: &mut dyn Write, message: &Value) -> Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
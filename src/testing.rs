@@ -0,0 +1,15 @@
+use std::{rc::Rc, cell::RefCell, io::Write};
+
+/// Ieșire pentru teste: păstrează tot ce s-a scris și poate fi citită și
+/// după ce o copie a fost dată mai departe ca `Box<dyn Write>`.
+#[derive(Clone, Default)]
+pub struct Shared(pub Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
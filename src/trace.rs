@@ -0,0 +1,201 @@
+use std::{rc::Rc, cell::RefCell, collections::HashMap};
+use anyhow::{Result, anyhow};
+
+use crate::interpreter::{Environment, Event, Hook, Object};
+use crate::parser::ASTNode;
+use crate::repl::exec;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TraceFormat {
+    Table,
+    Csv,
+    Markdown,
+}
+
+impl TraceFormat {
+    pub fn parse(name: &str) -> Result<TraceFormat> {
+        match name {
+            "tabel" | "table" => Ok(TraceFormat::Table),
+            "csv" => Ok(TraceFormat::Csv),
+            "md" | "markdown" => Ok(TraceFormat::Markdown),
+            _ => Err(anyhow!("Format necunoscut: `{}` (se acceptă tabel, csv, md).", name)),
+        }
+    }
+}
+
+struct Row {
+    line: usize,
+    stmt: String,
+    values: HashMap<String, String>,
+}
+
+/// Tabelul de valori: câte un rând după fiecare atribuire, `citește` și test
+/// de buclă.
+pub struct Table {
+    lines: Vec<String>,
+    /// Variabilele urmărite; `None` înseamnă toate.
+    watch: Option<Vec<String>>,
+    columns: Vec<String>,
+    rows: Vec<Row>,
+}
+
+struct Tracer(Rc<RefCell<Table>>);
+
+pub fn source_line(lines: &[String], line: usize) -> String {
+    lines.get(line.wrapping_sub(1))
+        .map(|l| l.trim_matches(|c: char| c.is_whitespace() || matches!(c, '│' | '└' | '┌')).to_string())
+        .unwrap_or_default()
+}
+
+impl Table {
+    pub fn new(input: &str, watch: Option<Vec<String>>) -> Table {
+        Table {
+            lines: input.lines().map(|l| l.to_string()).collect(),
+            columns: watch.clone().unwrap_or_default(),
+            watch,
+            rows: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, node: &ASTNode, test: Option<bool>, env: &Environment) {
+        let mut stmt = source_line(&self.lines, node.line);
+        if let Some(test) = test {
+            stmt.push_str(&format!(" → {}", Object::Bool(test)));
+        }
+
+        let mut values = HashMap::new();
+        let mut new_columns = Vec::new();
+        for (name, value) in &env.syms {
+            if let Some(watch) = &self.watch && !watch.contains(name) {
+                continue;
+            }
+            let value = match value.as_ref() {
+                Object::String(x) => format!("{:?}", x),
+                x => x.to_string(),
+            };
+            values.insert(name.clone(), value);
+            if !self.columns.contains(name) {
+                new_columns.push(name.clone());
+            }
+        }
+        new_columns.sort();
+        self.columns.extend(new_columns);
+
+        self.rows.push(Row { line: node.line, stmt, values });
+    }
+
+    fn cells(&self) -> (Vec<String>, Vec<Vec<String>>) {
+        let mut header = vec!["linia".to_string(), "instrucțiunea".to_string()];
+        header.extend(self.columns.iter().cloned());
+        let rows = self.rows.iter().map(|row| {
+            let mut cells = vec![row.line.to_string(), row.stmt.clone()];
+            cells.extend(self.columns.iter().map(|c| row.values.get(c).cloned().unwrap_or_default()));
+            cells
+        }).collect();
+        (header, rows)
+    }
+
+    pub fn render(&self, format: TraceFormat) -> String {
+        let (header, rows) = self.cells();
+        let mut out = String::new();
+        match format {
+            TraceFormat::Csv => {
+                let escape = |cell: &String| {
+                    if cell.contains([',', '"', '\n']) {
+                        format!("\"{}\"", cell.replace('"', "\"\""))
+                    } else {
+                        cell.clone()
+                    }
+                };
+                for row in std::iter::once(&header).chain(rows.iter()) {
+                    out.push_str(&row.iter().map(escape).collect::<Vec<_>>().join(","));
+                    out.push('\n');
+                }
+            }
+            TraceFormat::Markdown => {
+                let escape = |cell: &String| cell.replace('|', "\\|");
+                out.push_str(&format!("| {} |\n", header.iter().map(escape).collect::<Vec<_>>().join(" | ")));
+                out.push_str(&format!("|{}\n", "---|".repeat(header.len())));
+                for row in &rows {
+                    out.push_str(&format!("| {} |\n", row.iter().map(escape).collect::<Vec<_>>().join(" | ")));
+                }
+            }
+            TraceFormat::Table => {
+                let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+                for row in &rows {
+                    for (i, cell) in row.iter().enumerate() {
+                        widths[i] = widths[i].max(cell.chars().count());
+                    }
+                }
+                let line = |row: &Vec<String>| {
+                    row.iter().enumerate()
+                        .map(|(i, cell)| format!("{}{}", cell, " ".repeat(widths[i] - cell.chars().count())))
+                        .collect::<Vec<_>>().join(" │ ").trim_end().to_string()
+                };
+                out.push_str(&line(&header));
+                out.push('\n');
+                out.push_str(&widths.iter().map(|w| "─".repeat(*w)).collect::<Vec<_>>().join("─┼─"));
+                out.push('\n');
+                for row in &rows {
+                    out.push_str(&line(row));
+                    out.push('\n');
+                }
+            }
+        }
+        out
+    }
+}
+
+impl Hook for Tracer {
+    fn event(&mut self, event: Event, envb: &Rc<RefCell<Environment>>) -> Result<()> {
+        let env = envb.borrow();
+        let mut table = self.0.borrow_mut();
+        match event {
+            Event::Set(node) | Event::Read(node) => table.record(node, None, &env),
+            Event::LoopTest(node, test) => table.record(node, Some(test), &env),
+            Event::Statement(_) => (),
+        }
+        Ok(())
+    }
+}
+
+/// Rulează programul și întoarce tabelul de valori, chiar dacă execuția s-a
+/// oprit cu o eroare.
+pub fn trace(input: &str, envb: Rc<RefCell<Environment>>, watch: Option<Vec<String>>) -> (Rc<RefCell<Table>>, Result<Object>) {
+    let table = Rc::new(RefCell::new(Table::new(input, watch)));
+    envb.borrow_mut().hook = Some(Box::new(Tracer(Rc::clone(&table))));
+    let res = exec(input, envb);
+    (table, res)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, cell::RefCell};
+
+    use super::{trace, TraceFormat};
+    use crate::interpreter::Environment;
+
+    #[test]
+    fn trace_rows() {
+        let input = "s <- 0\npentru i <- 1, 2 execută\n  s <- s + i\n■\n";
+        let (table, res) = trace(input, Rc::new(RefCell::new(Environment::new())), None);
+        assert!(res.is_ok());
+        let csv = table.borrow().render(TraceFormat::Csv);
+        assert_eq!(csv, "linia,instrucțiunea,s,i\n\
+                         1,s <- 0,0,\n\
+                         2,\"pentru i <- 1, 2 execută\",0,1\n\
+                         2,\"pentru i <- 1, 2 execută → adevărat\",0,1\n\
+                         3,s <- s + i,1,1\n\
+                         2,\"pentru i <- 1, 2 execută → adevărat\",1,2\n\
+                         3,s <- s + i,3,2\n\
+                         2,\"pentru i <- 1, 2 execută → fals\",3,3\n");
+    }
+
+    #[test]
+    fn trace_watch() {
+        let input = "a <- 1\nb <- 2\n";
+        let (table, _) = trace(input, Rc::new(RefCell::new(Environment::new())), Some(vec!["b".to_string()]));
+        let md = table.borrow().render(TraceFormat::Markdown);
+        assert_eq!(md, "| linia | instrucțiunea | b |\n|---|---|---|\n| 1 | a <- 1 |  |\n| 2 | b <- 2 | 2 |\n");
+    }
+}
@@ -0,0 +1,465 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::builtins::lookup;
+use crate::lexer::VarType;
+use crate::ops::{Arithmetic, BinOp, Integers};
+use crate::parser::{ASTNode, ASTNodeValue};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Type {
+    Natural,
+    Integer,
+    Real,
+    Bool,
+    String,
+    /// Variabila primește valori de tipuri incompatibile.
+    Mixed,
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Type::Natural => "număr natural",
+            Type::Integer => "număr întreg",
+            Type::Real => "număr real",
+            Type::Bool => "boolean",
+            Type::String => "șir de caractere",
+            Type::Mixed => "tip variabil",
+        })
+    }
+}
+
+impl From<VarType> for Type {
+    fn from(t: VarType) -> Type {
+        match t {
+            VarType::Natural => Type::Natural,
+            VarType::Integer => Type::Integer,
+            VarType::Real => Type::Real,
+            VarType::String | VarType::Char => Type::String,
+        }
+    }
+}
+
+impl Type {
+    fn is_integer(&self) -> bool {
+        matches!(self, Type::Natural | Type::Integer)
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, Type::Natural | Type::Integer | Type::Real)
+    }
+
+    /// Cel mai mic tip care le cuprinde pe amândouă.
+    pub fn join(self, other: Type) -> Type {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (a, b) if a.is_integer() && b.is_integer() => Type::Integer,
+            (a, b) if a.is_number() && b.is_number() => Type::Real,
+            _ => Type::Mixed,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TypeError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Tipurile deduse pentru variabile și operațiile care nu au sens.
+#[derive(Default)]
+pub struct Types {
+    pub vars: HashMap<String, Type>,
+    pub errors: Vec<TypeError>,
+    arithmetic: Arithmetic,
+}
+
+impl Types {
+    fn error(&mut self, line: usize, message: String) {
+        self.errors.push(TypeError { line, message });
+    }
+
+    fn assign(&mut self, name: &str, t: Option<Type>) {
+        if let Some(t) = t {
+            let joined = match self.vars.get(name) {
+                Some(old) => old.join(t),
+                None => t,
+            };
+            self.vars.insert(name.to_string(), joined);
+        }
+    }
+
+    /// Tipul rezultatului pentru `+ - * / %`, după regulile din
+    /// `ops::binary`; `None` dacă operația nu are sens.
+    fn arithmetic(&self, op: BinOp, a: Type, b: Type) -> Option<Type> {
+        let cpp = matches!(self.arithmetic.integers, Integers::Cpp(_));
+        match (a, b) {
+            (Type::String, _) | (_, Type::String) if op == BinOp::Add => Some(Type::String),
+            (Type::String, n) | (n, Type::String) if op == BinOp::Multiply && n.is_integer() => Some(Type::String),
+            (a, b) if !a.is_number() || !b.is_number() => None,
+            _ if op == BinOp::Mod && cpp && !(a.is_integer() && b.is_integer()) => None,
+            _ if op == BinOp::Divide && !(cpp && a.is_integer() && b.is_integer()) => Some(Type::Real),
+            (Type::Real, _) | (_, Type::Real) => Some(Type::Real),
+            (Type::Natural, Type::Natural) if op != BinOp::Subtract => Some(Type::Natural),
+            _ => Some(Type::Integer),
+        }
+    }
+
+    /// Tipul lui `a ^ b`, ca în `builtins::power`: un exponent natural
+    /// păstrează numerele întregi, altfel rezultatul e real.
+    fn power(a: Type, b: Type) -> Option<Type> {
+        match (a, b) {
+            (Type::Natural, Type::Natural) => Some(Type::Natural),
+            (a, Type::Natural) if a.is_integer() => Some(Type::Integer),
+            (a, b) if a.is_number() && b.is_number() => Some(Type::Real),
+            _ => None,
+        }
+    }
+
+    /// Tipul rezultatului unei funcții predefinite, după ce verifică tipurile
+    /// argumentelor cunoscute.
+    fn call(&mut self, name: &str, operands: &[Option<Type>], line: usize) -> Option<Type> {
+        // Ce primește fiecare funcție: `n` număr, `i` număr întreg, `s` șir
+        // de caractere, `*` orice; ultimul se repetă.
+        let expected = match name {
+            "max" | "min" | "abs" | "sqrt" | "pow" => "n",
+            "cmmdc" | "prim" | "caracter" => "i",
+            "lungime" | "caută" | "majuscule" | "minuscule" | "cod" => "s",
+            "subșir" | "litera" => "si",
+            "scrie" | "citește" | "număr" | "text" => "*",
+            _ => return None,
+        };
+        if let Err(e) = lookup(name).unwrap().check_arity(operands.len()) {
+            self.error(line, e.to_string());
+            return None;
+        }
+        for (i, t) in operands.iter().enumerate() {
+            let Some(t) = t.filter(|t| *t != Type::Mixed) else { continue };
+            let (ok, what) = match expected.chars().nth(i).or(expected.chars().last()) {
+                Some('n') => (t.is_number(), "numere"),
+                Some('i') => (t.is_integer(), "numere întregi"),
+                Some('s') => (t == Type::String, "șiruri de caractere"),
+                _ => (true, ""),
+            };
+            if !ok {
+                self.error(line, format!("Funcția `{}` lucrează doar cu {}, nu cu un {}.", name, what, t));
+                return None;
+            }
+        }
+        let args: Vec<Type> = operands.iter().map(|t| t.filter(|t| *t != Type::Mixed)).collect::<Option<_>>()?;
+        match name {
+            "scrie" | "lungime" | "cmmdc" | "cod" => Some(Type::Natural),
+            "max" | "min" => args.into_iter().reduce(Type::join),
+            "abs" if args[0].is_integer() => Some(Type::Natural),
+            "abs" | "sqrt" => Some(Type::Real),
+            "pow" => Types::power(args[0], args[1]),
+            "prim" => Some(Type::Bool),
+            "citește" => None,
+            "caută" => Some(Type::Integer),
+            // Tipul depinde de textul convertit.
+            "număr" if args[0] == Type::String => None,
+            "număr" => Some(args[0]),
+            _ => Some(Type::String),
+        }
+    }
+
+    fn expr(&mut self, node: &ASTNode, line: usize) -> Option<Type> {
+        let mut operands = Vec::new();
+        if !matches!(node.value, ASTNodeValue::Set) {
+            for child in &node.children {
+                operands.push(self.expr(child, line));
+            }
+        }
+        let known = |t: &Option<Type>| t.filter(|t| *t != Type::Mixed);
+        match &node.value {
+            ASTNodeValue::Int(_) | ASTNodeValue::BigInt(_) => Some(Type::Natural),
+            ASTNodeValue::Float(_) => Some(Type::Real),
+            ASTNodeValue::String(_) => Some(Type::String),
+            ASTNodeValue::Bool(_) => Some(Type::Bool),
+            ASTNodeValue::Identifier(x) => self.vars.get(x).copied(),
+            ASTNodeValue::Set => {
+                self.stmt(node, line);
+                None
+            }
+            ASTNodeValue::FunctionCall(x) if lookup(x).is_some() => self.call(x, &operands, line),
+            ASTNodeValue::Not | ASTNodeValue::And | ASTNodeValue::Or => Some(Type::Bool),
+            ASTNodeValue::Equal | ASTNodeValue::NotEqual | ASTNodeValue::LessThan |
+            ASTNodeValue::GreaterThan | ASTNodeValue::LessThanEqual | ASTNodeValue::GreaterThanEqual => {
+                if let (Some(a), Some(b)) = (known(&operands[0]), known(&operands[1]))
+                    && (a == Type::String) != (b == Type::String) {
+                    self.error(line, format!("Compari un {} cu un {}; rezultatul nu depinde de valori.", a, b));
+                }
+                Some(Type::Bool)
+            }
+            ASTNodeValue::Subtract if operands.len() == 1 => match known(&operands[0])? {
+                Type::Natural => Some(Type::Integer),
+                t if t.is_number() => Some(t),
+                t => {
+                    self.error(line, format!("Semnul minus se poate pune doar în fața unui număr, nu a unui {}.", t));
+                    None
+                }
+            },
+            ASTNodeValue::Floor => match known(&operands[0])? {
+                Type::Real => Some(Type::Integer),
+                t if t.is_integer() => Some(t),
+                t => {
+                    self.error(line, format!("Partea întreagă se poate lua doar dintr-un număr, nu dintr-un {}.", t));
+                    None
+                }
+            },
+            value @ (ASTNodeValue::Add | ASTNodeValue::Subtract | ASTNodeValue::Multiply |
+                     ASTNodeValue::Divide | ASTNodeValue::Mod) => {
+                let (a, b) = (known(&operands[0])?, known(&operands[1])?);
+                let op = BinOp::from_node(value).unwrap();
+                let t = self.arithmetic(op, a, b);
+                if t.is_none() {
+                    self.error(line, format!("Operația `{}` nu are sens între un {} și un {}.", op.symbol(), a, b));
+                }
+                t
+            }
+            ASTNodeValue::Power => {
+                let (a, b) = (known(&operands[0])?, known(&operands[1])?);
+                if a == Type::String || b == Type::String {
+                    self.error(line, format!("Puterea nu are sens între un {} și un {}.", a, b));
+                    return None;
+                }
+                if matches!(self.arithmetic.integers, Integers::Cpp(_)) {
+                    return Some(Type::Real);
+                }
+                Types::power(a, b)
+            }
+            ASTNodeValue::Decimals => {
+                if let Some(t) = known(&operands[1]) && !t.is_integer() {
+                    self.error(line, format!("Numărul de zecimale trebuie să fie un număr natural, nu un {}.", t));
+                }
+                Some(Type::String)
+            }
+            _ => None,
+        }
+    }
+
+    fn for_header(&mut self, node: &ASTNode, line: usize) {
+        let Some([target, start]) = node.children.first().map(|set| set.children.as_slice()) else {
+            return;
+        };
+        let mut bounds = vec![self.expr(start, line)];
+        for child in node.children.iter().skip(1).filter(|c| c.value != ASTNodeValue::Program) {
+            bounds.push(self.expr(child, line));
+        }
+        // Cu un pas negativ sau real, contorul devine întreg sau real.
+        let mut counter = Type::Natural;
+        for t in bounds.into_iter().flatten() {
+            if t.is_number() {
+                counter = counter.join(t);
+            } else if t != Type::Mixed {
+                self.error(line, format!("`pentru` parcurge doar numere, nu un {}.", t));
+            }
+        }
+        if let ASTNodeValue::Identifier(x) = &target.value {
+            self.assign(x, Some(counter));
+        }
+    }
+
+    fn stmt(&mut self, node: &ASTNode, line: usize) {
+        let line = if node.line > 0 { node.line } else { line };
+        match &node.value {
+            ASTNodeValue::Program => {
+                for child in &node.children {
+                    self.stmt(child, line);
+                }
+            }
+            ASTNodeValue::Set => {
+                let (targets, values) = node.assignment();
+                let types: Vec<_> = values.iter().map(|v| self.expr(v, line)).collect();
+                for (target, t) in targets.iter().zip(types) {
+                    if let ASTNodeValue::Identifier(x) = &target.value {
+                        self.assign(x, t);
+                    }
+                }
+            }
+            ASTNodeValue::Swap => {
+                if let [a, b] = node.children.as_slice() && let (ASTNodeValue::Identifier(a), ASTNodeValue::Identifier(b)) = (&a.value, &b.value) {
+                    let (ta, tb) = (self.vars.get(a).copied(), self.vars.get(b).copied());
+                    self.assign(a, tb);
+                    self.assign(b, ta);
+                }
+            }
+            ASTNodeValue::FunctionCall(name) if lookup(name).is_some_and(|b| b.assigns()) => {
+                for arg in &node.children {
+                    match &arg.value {
+                        // Fără adnotare, tipul depinde de datele citite.
+                        ASTNodeValue::Identifier(_) => (),
+                        ASTNodeValue::Cast(x, t) => self.assign(x, Some(Type::from(*t))),
+                        _ => (),
+                    }
+                }
+            }
+            ASTNodeValue::For => {
+                self.for_header(node, line);
+                if let Some(body) = node.children.last() && body.value == ASTNodeValue::Program {
+                    self.stmt(body, line);
+                }
+            }
+            ASTNodeValue::Execute => {
+                if let Some(body) = node.children.first() {
+                    self.stmt(body, line);
+                }
+                if let Some(header) = node.children.get(1) {
+                    let line = if header.line > 0 { header.line } else { line };
+                    match (&header.value, header.children.first()) {
+                        (ASTNodeValue::For, _) => self.for_header(header, line),
+                        (_, Some(cond)) => { self.expr(cond, line); }
+                        _ => (),
+                    }
+                }
+            }
+            ASTNodeValue::If | ASTNodeValue::While | ASTNodeValue::Until => {
+                if let Some(cond) = node.children.first() {
+                    self.expr(cond, line);
+                }
+                for child in node.children.iter().skip(1) {
+                    self.stmt(child, line);
+                }
+            }
+            _ => { self.expr(node, line); }
+        }
+    }
+}
+
+/// Deduce tipurile variabilelor din valori, adnotările lui `citește` și
+/// regulile operațiilor. O variabilă poate fi folosită înainte de atribuirea
+/// care îi dă tipul (în bucle), așa că programul se parcurge până când
+/// tipurile nu se mai schimbă. Regulile de calcul sunt cele alese la rulare:
+/// cu `--cpp`, `/` între întregi dă un întreg, `^` un real, iar `%` cere
+/// numere întregi.
+pub fn infer_with(root: &ASTNode, arithmetic: Arithmetic) -> Types {
+    let mut types = Types { arithmetic, ..Types::default() };
+    // Tipurile doar cresc (natural → întreg → real → variabil), deci câteva
+    // treceri ajung.
+    for _ in 0..8 {
+        let before = types.vars.clone();
+        types.errors.clear();
+        types.stmt(root, 0);
+        if types.vars == before {
+            break;
+        }
+    }
+    types.errors.sort_by_key(|e| e.line);
+    types.errors.dedup_by(|a, b| a.line == b.line && a.message == b.message);
+    types
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{infer_with, Type, Types};
+    use crate::ops::{Arithmetic, Integers};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn types(input: &str) -> Types {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let root = parser.parse();
+        assert!(parser.errors.is_empty(), "{:?}", parser.errors);
+        infer_with(&root, Arithmetic::default())
+    }
+
+    #[test]
+    fn variables() {
+        let t = types("citește n (număr natural), x (număr real)\ns <- 0\nm <- 0\npentru i <- 1, n execută\n  s <- s + i\n  m <- m + x / 2\n■\n\
+                       k <- -n\nnume <- 'a' + n\nok <- s > 3\nr <- [x]\n");
+        let get = |name: &str| t.vars.get(name).copied();
+        assert_eq!(get("n"), Some(Type::Natural));
+        assert_eq!(get("s"), Some(Type::Natural));
+        assert_eq!(get("m"), Some(Type::Real));
+        assert_eq!(get("i"), Some(Type::Natural));
+        assert_eq!(get("k"), Some(Type::Integer));
+        assert_eq!(get("nume"), Some(Type::String));
+        assert_eq!(get("ok"), Some(Type::Bool));
+        assert_eq!(get("r"), Some(Type::Integer));
+        assert!(t.errors.is_empty());
+    }
+
+    #[test]
+    fn for_counters() {
+        let t = types("pentru i <- 10, 1, -1 execută\n■\npentru x <- 0, 1, 0.25 execută\n■\npentru k <- 1, 5 execută\n■\n");
+        assert_eq!(t.vars.get("i"), Some(&Type::Integer));
+        assert_eq!(t.vars.get("x"), Some(&Type::Real));
+        assert_eq!(t.vars.get("k"), Some(&Type::Natural));
+        assert!(t.errors.is_empty());
+    }
+
+    #[test]
+    fn mismatches() {
+        let t = types("citește s (șir de caractere)\nx <- 5 / s\npentru i <- 1, s execută\n  scrie i\n■\ny <- 1\ny <- 'a'\nz <- [y]\n");
+        let errors: Vec<_> = t.errors.iter().map(|e| e.line).collect();
+        assert_eq!(errors, vec![2, 3]);
+        assert_eq!(t.vars.get("y"), Some(&Type::Mixed));
+    }
+
+    #[test]
+    fn arithmetic() {
+        let t = types("citește n (număr natural), k (număr întreg)\na <- n * n\nb <- k * n\nc <- n / n\nd <- n % 3\ne <- 'ab' * n\nf <- n * 'ab'\n");
+        let get = |name: &str| t.vars.get(name).copied();
+        assert_eq!(get("a"), Some(Type::Natural));
+        assert_eq!(get("b"), Some(Type::Integer));
+        assert_eq!(get("c"), Some(Type::Real));
+        assert_eq!(get("d"), Some(Type::Natural));
+        assert_eq!(get("e"), Some(Type::String));
+        assert_eq!(get("f"), Some(Type::String));
+        assert!(t.errors.is_empty());
+
+        let mut parser = Parser::new(Lexer::new("citește n (număr natural)\na <- n / 2\nb <- n / 2.0\nc <- n ^ 2\nd <- b % 2\n".to_string()));
+        let t = infer_with(&parser.parse(), Arithmetic { integers: Integers::Cpp(32), exact: false });
+        assert_eq!(t.vars.get("a"), Some(&Type::Natural));
+        assert_eq!(t.vars.get("b"), Some(&Type::Real));
+        assert_eq!(t.vars.get("c"), Some(&Type::Real));
+        assert_eq!(t.errors.iter().map(|e| e.line).collect::<Vec<_>>(), vec![5]);
+
+        let t = types("s <- 'a'\nx <- s - 1\ny <- s % 2\nz <- adevărat + 1\nw <- s * 1.5\nv <- -s\n");
+        let errors: Vec<_> = t.errors.iter().map(|e| e.line).collect();
+        assert_eq!(errors, vec![2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn math() {
+        let t = types("citește n (număr natural), x (număr real)\na <- n ^ 2\nb <- 2 ^ -n\nc <- √n\nd <- |n - 5|\ne <- max(n, x)\nf <- cmmdc(n, 4)\ng <- prim(n)\n");
+        let get = |name: &str| t.vars.get(name).copied();
+        assert_eq!(get("a"), Some(Type::Natural));
+        assert_eq!(get("c"), Some(Type::Real));
+        assert_eq!(get("d"), Some(Type::Natural));
+        assert_eq!(get("e"), Some(Type::Real));
+        assert_eq!(get("f"), Some(Type::Natural));
+        assert_eq!(get("g"), Some(Type::Bool));
+        assert!(t.errors.is_empty());
+
+        let t = types("s <- 'a'\nx <- s ^ 2\ny <- max(1, s)\nz <- pow(2)\n");
+        let errors: Vec<_> = t.errors.iter().map(|e| e.line).collect();
+        assert_eq!(errors, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn strings() {
+        let t = types("citește s (șir de caractere)\nn <- lungime(s)\nc <- litera(s, n - 1)\ni <- caută(s, 'a')\nk <- cod(c)\nx <- număr(s)\ny <- număr(2.5)\n");
+        let get = |name: &str| t.vars.get(name).copied();
+        assert_eq!(get("n"), Some(Type::Natural));
+        assert_eq!(get("c"), Some(Type::String));
+        assert_eq!(get("i"), Some(Type::Integer));
+        assert_eq!(get("k"), Some(Type::Natural));
+        assert_eq!(get("x"), None);
+        assert_eq!(get("y"), Some(Type::Real));
+        assert!(t.errors.is_empty());
+
+        let t = types("a <- lungime(5)\nb <- litera('abc', 1.5)\nc <- caracter('a')\nd <- subșir('abc')\n");
+        let errors: Vec<_> = t.errors.iter().map(|e| e.line).collect();
+        assert_eq!(errors, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn parallel_assignment() {
+        let t = types("a <- 1\ns <- 'x'\na, s <- s, a\nb <- 2.5\nc <- 1\nb ↔ c\n");
+        assert_eq!(t.vars.get("a"), Some(&Type::Mixed));
+        assert_eq!(t.vars.get("s"), Some(&Type::Mixed));
+        assert_eq!(t.vars.get("b"), Some(&Type::Real));
+        assert_eq!(t.vars.get("c"), Some(&Type::Real));
+    }
+}